@@ -13,7 +13,73 @@ fn main() {
 
     const INCLUDES: &[&str] = &["gbm.h"];
 
+    // `GBM_FORMAT_*` macros expand to `__gbm_fourcc_code`, which bindgen can't evaluate
     const MACROS: &[&str] = &[
+        "GBM_FORMAT_C8",
+        "GBM_FORMAT_R8",
+        "GBM_FORMAT_R16",
+        "GBM_FORMAT_GR88",
+        "GBM_FORMAT_RG1616",
+        "GBM_FORMAT_GR1616",
+        "GBM_FORMAT_RGB332",
+        "GBM_FORMAT_BGR233",
+        "GBM_FORMAT_XRGB4444",
+        "GBM_FORMAT_XBGR4444",
+        "GBM_FORMAT_RGBX4444",
+        "GBM_FORMAT_BGRX4444",
+        "GBM_FORMAT_ARGB4444",
+        "GBM_FORMAT_ABGR4444",
+        "GBM_FORMAT_RGBA4444",
+        "GBM_FORMAT_BGRA4444",
+        "GBM_FORMAT_XRGB1555",
+        "GBM_FORMAT_XBGR1555",
+        "GBM_FORMAT_RGBX5551",
+        "GBM_FORMAT_BGRX5551",
+        "GBM_FORMAT_ARGB1555",
+        "GBM_FORMAT_ABGR1555",
+        "GBM_FORMAT_RGBA5551",
+        "GBM_FORMAT_BGRA5551",
+        "GBM_FORMAT_RGB565",
+        "GBM_FORMAT_BGR565",
+        "GBM_FORMAT_RGB888",
+        "GBM_FORMAT_BGR888",
+        "GBM_FORMAT_XRGB8888",
+        "GBM_FORMAT_XBGR8888",
+        "GBM_FORMAT_RGBX8888",
+        "GBM_FORMAT_BGRX8888",
+        "GBM_FORMAT_ARGB8888",
+        "GBM_FORMAT_ABGR8888",
+        "GBM_FORMAT_RGBA8888",
+        "GBM_FORMAT_BGRA8888",
+        "GBM_FORMAT_XRGB2101010",
+        "GBM_FORMAT_XBGR2101010",
+        "GBM_FORMAT_RGBX1010102",
+        "GBM_FORMAT_BGRX1010102",
+        "GBM_FORMAT_ARGB2101010",
+        "GBM_FORMAT_ABGR2101010",
+        "GBM_FORMAT_RGBA1010102",
+        "GBM_FORMAT_BGRA1010102",
+        "GBM_FORMAT_XBGR16161616F",
+        "GBM_FORMAT_ABGR16161616F",
+        "GBM_FORMAT_YUYV",
+        "GBM_FORMAT_YVYU",
+        "GBM_FORMAT_UYVY",
+        "GBM_FORMAT_VYUY",
+        "GBM_FORMAT_AYUV",
+        "GBM_FORMAT_NV12",
+        "GBM_FORMAT_NV21",
+        "GBM_FORMAT_NV16",
+        "GBM_FORMAT_NV61",
+        "GBM_FORMAT_YUV410",
+        "GBM_FORMAT_YVU410",
+        "GBM_FORMAT_YUV411",
+        "GBM_FORMAT_YVU411",
+        "GBM_FORMAT_YUV420",
+        "GBM_FORMAT_YVU420",
+        "GBM_FORMAT_YUV422",
+        "GBM_FORMAT_YVU422",
+        "GBM_FORMAT_YUV444",
+        "GBM_FORMAT_YVU444",
         "GBM_BO_IMPORT_WL_BUFFER",
         "GBM_BO_IMPORT_EGL_IMAGE",
         "GBM_BO_IMPORT_FD",
@@ -1,6 +1,71 @@
 /* automatically generated by rust-bindgen 0.69.5 */
 
 pub const GBM_FORMAT_BIG_ENDIAN: u32 = 2147483648;
+pub const GBM_FORMAT_C8: u32 = 538982467;
+pub const GBM_FORMAT_R8: u32 = 538982482;
+pub const GBM_FORMAT_R16: u32 = 540422482;
+pub const GBM_FORMAT_GR88: u32 = 943215175;
+pub const GBM_FORMAT_RG1616: u32 = 842221394;
+pub const GBM_FORMAT_GR1616: u32 = 842224199;
+pub const GBM_FORMAT_RGB332: u32 = 943867730;
+pub const GBM_FORMAT_BGR233: u32 = 944916290;
+pub const GBM_FORMAT_XRGB4444: u32 = 842093144;
+pub const GBM_FORMAT_XBGR4444: u32 = 842089048;
+pub const GBM_FORMAT_RGBX4444: u32 = 842094674;
+pub const GBM_FORMAT_BGRX4444: u32 = 842094658;
+pub const GBM_FORMAT_ARGB4444: u32 = 842093121;
+pub const GBM_FORMAT_ABGR4444: u32 = 842089025;
+pub const GBM_FORMAT_RGBA4444: u32 = 842088786;
+pub const GBM_FORMAT_BGRA4444: u32 = 842088770;
+pub const GBM_FORMAT_XRGB1555: u32 = 892424792;
+pub const GBM_FORMAT_XBGR1555: u32 = 892420696;
+pub const GBM_FORMAT_RGBX5551: u32 = 892426322;
+pub const GBM_FORMAT_BGRX5551: u32 = 892426306;
+pub const GBM_FORMAT_ARGB1555: u32 = 892424769;
+pub const GBM_FORMAT_ABGR1555: u32 = 892420673;
+pub const GBM_FORMAT_RGBA5551: u32 = 892420434;
+pub const GBM_FORMAT_BGRA5551: u32 = 892420418;
+pub const GBM_FORMAT_RGB565: u32 = 909199186;
+pub const GBM_FORMAT_BGR565: u32 = 909199170;
+pub const GBM_FORMAT_RGB888: u32 = 875710290;
+pub const GBM_FORMAT_BGR888: u32 = 875710274;
+pub const GBM_FORMAT_XRGB8888: u32 = 875713112;
+pub const GBM_FORMAT_XBGR8888: u32 = 875709016;
+pub const GBM_FORMAT_RGBX8888: u32 = 875714642;
+pub const GBM_FORMAT_BGRX8888: u32 = 875714626;
+pub const GBM_FORMAT_ARGB8888: u32 = 875713089;
+pub const GBM_FORMAT_ABGR8888: u32 = 875708993;
+pub const GBM_FORMAT_RGBA8888: u32 = 875708754;
+pub const GBM_FORMAT_BGRA8888: u32 = 875708738;
+pub const GBM_FORMAT_XRGB2101010: u32 = 808669784;
+pub const GBM_FORMAT_XBGR2101010: u32 = 808665688;
+pub const GBM_FORMAT_RGBX1010102: u32 = 808671314;
+pub const GBM_FORMAT_BGRX1010102: u32 = 808671298;
+pub const GBM_FORMAT_ARGB2101010: u32 = 808669761;
+pub const GBM_FORMAT_ABGR2101010: u32 = 808665665;
+pub const GBM_FORMAT_RGBA1010102: u32 = 808665426;
+pub const GBM_FORMAT_BGRA1010102: u32 = 808665410;
+pub const GBM_FORMAT_XBGR16161616F: u32 = 1211384408;
+pub const GBM_FORMAT_ABGR16161616F: u32 = 1211384385;
+pub const GBM_FORMAT_YUYV: u32 = 1448695129;
+pub const GBM_FORMAT_YVYU: u32 = 1431918169;
+pub const GBM_FORMAT_UYVY: u32 = 1498831189;
+pub const GBM_FORMAT_VYUY: u32 = 1498765654;
+pub const GBM_FORMAT_AYUV: u32 = 1448433985;
+pub const GBM_FORMAT_NV12: u32 = 842094158;
+pub const GBM_FORMAT_NV21: u32 = 825382478;
+pub const GBM_FORMAT_NV16: u32 = 909203022;
+pub const GBM_FORMAT_NV61: u32 = 825644622;
+pub const GBM_FORMAT_YUV410: u32 = 961959257;
+pub const GBM_FORMAT_YVU410: u32 = 961893977;
+pub const GBM_FORMAT_YUV411: u32 = 825316697;
+pub const GBM_FORMAT_YVU411: u32 = 825316953;
+pub const GBM_FORMAT_YUV420: u32 = 842093913;
+pub const GBM_FORMAT_YVU420: u32 = 842094169;
+pub const GBM_FORMAT_YUV422: u32 = 909202777;
+pub const GBM_FORMAT_YVU422: u32 = 909203033;
+pub const GBM_FORMAT_YUV444: u32 = 875713881;
+pub const GBM_FORMAT_YVU444: u32 = 875714137;
 pub const GBM_BO_IMPORT_WL_BUFFER: u32 = 21761;
 pub const GBM_BO_IMPORT_EGL_IMAGE: u32 = 21762;
 pub const GBM_BO_IMPORT_FD: u32 = 21763;
@@ -1,3 +1,8 @@
+//! Raw bindings to libgbm
+//!
+//! The `GBM_FORMAT_*` constants are identical to the DRM fourcc codes of the same name
+//! in `drm_fourcc.h`, e.g. `GBM_FORMAT_XRGB8888 == DRM_FORMAT_XRGB8888`.
+
 #![allow(non_camel_case_types, non_upper_case_globals)]
 // Allowed this because some bindgen tests looks like
 // it tries to dereference null pointers but actually
@@ -12,3 +17,6 @@ include!("bindings.rs");
 
 #[link(name = "gbm")]
 extern "C" {}
+
+mod runtime;
+pub use runtime::*;
@@ -0,0 +1,93 @@
+use std::ffi::CStr;
+
+/// Presence of libgbm functions, that are not provided by all versions of libgbm
+///
+/// Returned by [`runtime_capabilities()`]. Calling a function missing from the loaded
+/// libgbm aborts the process, if the executable was linked with lazy binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct RuntimeCaps {
+    /// `gbm_bo_create_with_modifiers`, added in Mesa 17.1
+    pub bo_create_with_modifiers: bool,
+    /// `gbm_bo_create_with_modifiers2`, added in Mesa 21.3
+    pub bo_create_with_modifiers2: bool,
+    /// `gbm_surface_create_with_modifiers`, added in Mesa 17.1
+    pub surface_create_with_modifiers: bool,
+    /// `gbm_surface_create_with_modifiers2`, added in Mesa 21.3
+    pub surface_create_with_modifiers2: bool,
+    /// `gbm_bo_get_modifier`, added in Mesa 17.1
+    pub bo_get_modifier: bool,
+    /// `gbm_bo_get_plane_count`, added in Mesa 17.1
+    pub bo_get_plane_count: bool,
+    /// `gbm_bo_get_fd_for_plane`, added in Mesa 21.1
+    pub bo_get_fd_for_plane: bool,
+    /// `gbm_device_get_format_modifier_plane_count`
+    pub device_get_format_modifier_plane_count: bool,
+}
+
+/// Check which version-gated functions the loaded libgbm provides
+///
+/// Unlike the bindings, which are generated for the libgbm present at build time,
+/// this looks up the functions in the libgbm loaded at runtime with [`has_symbol()`].
+pub fn runtime_capabilities() -> RuntimeCaps {
+    let has = |name: &[u8]| has_symbol(CStr::from_bytes_with_nul(name).unwrap());
+    RuntimeCaps {
+        bo_create_with_modifiers: has(b"gbm_bo_create_with_modifiers\0"),
+        bo_create_with_modifiers2: has(b"gbm_bo_create_with_modifiers2\0"),
+        surface_create_with_modifiers: has(b"gbm_surface_create_with_modifiers\0"),
+        surface_create_with_modifiers2: has(b"gbm_surface_create_with_modifiers2\0"),
+        bo_get_modifier: has(b"gbm_bo_get_modifier\0"),
+        bo_get_plane_count: has(b"gbm_bo_get_plane_count\0"),
+        bo_get_fd_for_plane: has(b"gbm_bo_get_fd_for_plane\0"),
+        device_get_format_modifier_plane_count: has(
+            b"gbm_device_get_format_modifier_plane_count\0",
+        ),
+    }
+}
+
+/// Check whether the loaded libgbm provides the function `name`
+///
+/// The symbol is looked up in the global scope of the process with `dlsym` and, if
+/// libgbm was loaded with `RTLD_LOCAL`, e.g. as dependency of a plugin, in the already
+/// loaded `libgbm.so.1`.
+pub fn has_symbol(name: &CStr) -> bool {
+    unsafe {
+        if !libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr()).is_null() {
+            return true;
+        }
+        let handle = libc::dlopen(
+            b"libgbm.so.1\0".as_ptr() as *const libc::c_char,
+            libc::RTLD_LAZY | libc::RTLD_NOLOAD,
+        );
+        if handle.is_null() {
+            return false;
+        }
+        let present = !libc::dlsym(handle, name.as_ptr()).is_null();
+        libc::dlclose(handle);
+        present
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{has_symbol, runtime_capabilities};
+    use std::ffi::CStr;
+
+    #[test]
+    fn missing_symbol() {
+        let name = CStr::from_bytes_with_nul(b"gbm_does_not_exist\0").unwrap();
+        assert!(!has_symbol(name));
+    }
+
+    #[test]
+    fn locally_loaded_libgbm() {
+        // Load libgbm with `RTLD_LOCAL`, so it is only found through the fallback
+        let name = CStr::from_bytes_with_nul(b"libgbm.so.1\0").unwrap();
+        let handle = unsafe { libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            return;
+        }
+        assert!(runtime_capabilities().bo_get_plane_count);
+        unsafe { libc::dlclose(handle) };
+    }
+}
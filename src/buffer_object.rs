@@ -1,27 +1,54 @@
 #![allow(clippy::unnecessary_cast)]
 
-use crate::{AsRaw, Format, Modifier, Ptr};
+use crate::{AsRaw, ChannelOrder, Device, DmabufImportBuilder, Format, Modifier, Ptr};
 
 #[cfg(feature = "drm-support")]
 use drm::buffer::{Buffer as DrmBuffer, Handle, PlanarBuffer as DrmPlanarBuffer};
-use std::os::unix::io::{BorrowedFd, FromRawFd, OwnedFd};
+use smallvec::SmallVec;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
 
 use std::error;
 use std::fmt;
-use std::io::{Error as IoError, Result as IoResult};
+use std::io::{self, Error as IoError, Result as IoResult};
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::ptr;
 use std::slice;
+#[cfg(debug_assertions)]
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
 
 /// A GBM buffer object
+#[must_use = "dropping this immediately frees the GPU buffer"]
 pub struct BufferObject<T: 'static> {
-    // Declare `ffi` first so it is dropped before `_device`
+    // Declare `persistent_mapping` first so it is unmapped before `ffi` is destroyed
+    pub(crate) persistent_mapping: Option<PersistentMapping>,
+    // Declare `ffi` before `_device` so it is dropped first
     pub(crate) ffi: Ptr<ffi::gbm_bo>,
     pub(crate) _device: Ptr<ffi::gbm_device>,
+    pub(crate) device_fd: RawFd,
     pub(crate) _userdata: PhantomData<T>,
+    pub(crate) usage: Option<BufferObjectFlags>,
+    pub(crate) plane_count: AtomicU32,
+    pub(crate) requested_modifiers: Option<Box<[Modifier]>>,
+    pub(crate) cached_fd: Mutex<Option<OwnedFd>>,
+    // Set for handles created by `dup`, whose userdata type doesn't match the stored userdata
+    pub(crate) foreign_userdata: bool,
+    // Set for front buffers locked from a surface, which keeps their userdata across locks
+    pub(crate) surface_owned: bool,
+    // Set by cpu writes and cleared by synchronization, to detect read-after-write hazards
+    #[cfg(debug_assertions)]
+    pub(crate) cpu_dirty: AtomicBool,
+    // Regions currently mapped for reading through this handle, to detect overlapping maps
+    #[cfg(debug_assertions)]
+    pub(crate) read_maps: Mutex<Vec<(u32, u32, u32, u32)>>,
 }
 
+// Marks `BufferObject::plane_count` as not yet queried
+const UNKNOWN_PLANE_COUNT: u32 = u32::MAX;
+
 impl<T> fmt::Debug for BufferObject<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("BufferObject")
@@ -37,6 +64,25 @@ impl<T> fmt::Debug for BufferObject<T> {
     }
 }
 
+/// One-line summary, e.g. `1280x720 ARGB8888 mod=LINEAR planes=1 stride=5120`
+impl<T> fmt::Display for BufferObject<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{} ", self.width(), self.height())?;
+        let fourcc = unsafe { ffi::gbm_bo_get_format(*self.ffi) };
+        match crate::fourcc_to_format(fourcc) {
+            Some(format) => write!(f, "{}", format!("{:?}", format).to_uppercase())?,
+            None => write!(f, "{:#010x}", fourcc)?,
+        }
+        write!(
+            f,
+            " mod={} planes={} stride={}",
+            crate::modifier_to_string(self.modifier()),
+            self.plane_count(),
+            self.stride()
+        )
+    }
+}
+
 bitflags! {
     /// Flags to indicate the intended use for the buffer - these are passed into
     /// [`Device::create_buffer_object()`].
@@ -69,6 +115,83 @@ bitflags! {
 /// Abstraction representing the handle to a buffer allocated by the manager
 pub type BufferObjectHandle = ffi::gbm_bo_handle;
 
+/// Layout information of a single plane of a [`BufferObject`]
+#[derive(Clone, Copy)]
+pub struct PlaneInfo {
+    /// Index of the plane
+    pub index: i32,
+    /// Offset of the plane
+    pub offset: u32,
+    /// Stride of the plane
+    pub stride: u32,
+    /// Handle of the plane
+    pub handle: BufferObjectHandle,
+}
+
+impl fmt::Debug for PlaneInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PlaneInfo")
+            .field("index", &self.index)
+            .field("offset", &self.offset)
+            .field("stride", &self.stride)
+            .field("handle", &unsafe { self.handle.u32_ })
+            .finish()
+    }
+}
+
+/// Iterator over the planes of a [`BufferObject`]
+///
+/// Created by [`BufferObject::planes()`].
+pub struct Planes<'a, T: 'static> {
+    bo: &'a BufferObject<T>,
+    next: i32,
+    count: i32,
+}
+
+impl<'a, T: 'static> fmt::Debug for Planes<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Planes")
+            .field("bo", &format_args!("{:p}", self.bo.ffi))
+            .field("next", &self.next)
+            .field("count", &self.count)
+            .finish()
+    }
+}
+
+impl<'a, T: 'static> Iterator for Planes<'a, T> {
+    type Item = PlaneInfo;
+
+    fn next(&mut self) -> Option<PlaneInfo> {
+        if self.next >= self.count {
+            return None;
+        }
+        let index = self.next;
+        self.next += 1;
+        Some(PlaneInfo {
+            index,
+            offset: self.bo.offset(index),
+            stride: self.bo.stride_for_plane(index),
+            handle: self.bo.handle_for_plane(index),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.count - self.next).max(0) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T: 'static> ExactSizeIterator for Planes<'a, T> {}
+
+impl<'a, T: 'static> IntoIterator for &'a BufferObject<T> {
+    type Item = PlaneInfo;
+    type IntoIter = Planes<'a, T>;
+
+    fn into_iter(self) -> Planes<'a, T> {
+        self.planes()
+    }
+}
+
 enum BORef<'a, T: 'static> {
     Ref(&'a BufferObject<T>),
     Mut(&'a mut BufferObject<T>),
@@ -84,6 +207,59 @@ pub struct MappedBufferObject<'a, T: 'static> {
     width: u32,
     x: u32,
     y: u32,
+    pos: usize,
+    // Set once mutable access to the mapped memory was handed out
+    written: bool,
+}
+
+// A mapping of the whole buffer object kept across frames, see `BufferObject::persistent_map`.
+// It is owned by the `BufferObject`, which unmaps it before destroying `bo`.
+pub(crate) struct PersistentMapping {
+    bo: *mut ffi::gbm_bo,
+    ptr: *mut u8,
+    data: *mut ::libc::c_void,
+    stride: u32,
+    // Set when a view of the mapping was written to since it was mapped
+    dirty: bool,
+}
+
+// SAFETY: The mapped memory is only accessed through a `PersistentMapGuard` borrowing the
+// owning `BufferObject` mutably, so it is never accessed from multiple threads at once.
+unsafe impl Send for PersistentMapping {}
+unsafe impl Sync for PersistentMapping {}
+
+impl PersistentMapping {
+    unsafe fn new(bo: *mut ffi::gbm_bo, width: u32, height: u32) -> IoResult<PersistentMapping> {
+        let mut data: *mut ::libc::c_void = ptr::null_mut();
+        let mut stride = 0;
+        let ptr = ffi::gbm_bo_map(
+            bo,
+            0,
+            0,
+            width,
+            height,
+            ffi::gbm_bo_transfer_flags::GBM_BO_TRANSFER_READ_WRITE as u32,
+            &mut stride as *mut _,
+            &mut data as *mut _,
+        );
+        if ptr.is_null() {
+            Err(IoError::last_os_error())
+        } else {
+            Ok(PersistentMapping {
+                bo,
+                ptr: ptr as *mut u8,
+                data,
+                stride,
+                dirty: false,
+            })
+        }
+    }
+}
+
+impl Drop for PersistentMapping {
+    fn drop(&mut self) {
+        unsafe { ffi::gbm_bo_unmap(self.bo, self.data) }
+    }
 }
 
 impl<'a, T> fmt::Debug for MappedBufferObject<'a, T> {
@@ -142,7 +318,393 @@ impl<'a, T: 'static> MappedBufferObject<'a, T> {
 
     /// Mutable access to the underlying image buffer
     pub fn buffer_mut(&mut self) -> &mut [u8] {
+        self.written = true;
+        self.buffer
+    }
+
+    /// Mutable access to the underlying image buffer as possibly uninitialized memory
+    ///
+    /// Memory mapped by libgbm is never uninitialized from Rust's point of view, even for
+    /// a freshly allocated buffer object, so [`Self::buffer_mut()`] is always fine to use.
+    /// This is meant for write-only fills overwriting the whole region with APIs writing
+    /// to uninitialized memory, e.g. decoders filling a `&mut [MaybeUninit<u8>]`, without
+    /// having to initialize the mapping first.
+    ///
+    /// # Safety
+    ///
+    /// No uninitialized value may be written to the returned slice, e.g. through
+    /// [`MaybeUninit::uninit()`](std::mem::MaybeUninit::uninit()), as the mapped memory
+    /// is accessed as initialized bytes afterwards.
+    ///
+    /// Regions mapped read-only with [`BufferObject::map_guard()`] don't provide this:
+    ///
+    /// ```compile_fail
+    /// # fn fill(bo: &gbm::BufferObject<()>) {
+    /// let mut mapping = bo.map_guard(0, 0, bo.width(), bo.height()).unwrap();
+    /// let _ = unsafe { mapping.buffer_uninit() };
+    /// # }
+    /// ```
+    pub unsafe fn buffer_uninit(&mut self) -> &mut [std::mem::MaybeUninit<u8>] {
+        self.written = true;
+        slice::from_raw_parts_mut(
+            self.buffer.as_mut_ptr() as *mut std::mem::MaybeUninit<u8>,
+            self.buffer.len(),
+        )
+    }
+
+    /// Whether the mapped memory might have been written to through this mapping
+    ///
+    /// This is a conservative heuristic: it is set as soon as mutable access to the
+    /// mapped memory or the buffer object is handed out, e.g. by [`Self::buffer_mut()`],
+    /// [`Self::get_mut()`], [`io::Write`] or through [`DerefMut`], regardless of whether
+    /// anything was actually written. Mappings that were never written to skip the
+    /// bookkeeping of cpu writes on drop, i.e. they don't trigger the read-after-write
+    /// warning of [`BufferObject::map()`].
+    ///
+    /// libgbm decides whether the mapped memory is written back on unmap based on the
+    /// access requested when mapping, so drivers mapping through a staging copy still
+    /// copy it back when unmapping a mutable mapping, that was never written to.
+    pub fn was_written(&self) -> bool {
+        self.written
+    }
+
+    /// Raw pointer to the start of the mapped region
+    ///
+    /// Rows are [`Self::stride()`] bytes apart. The pointer is valid for
+    /// `stride * height` bytes until the mapping is dropped.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.buffer.as_ptr()
+    }
+
+    /// Mutable raw pointer to the start of the mapped region
+    ///
+    /// Rows are [`Self::stride()`] bytes apart. The pointer is valid for
+    /// `stride * height` bytes until the mapping is dropped.
+    pub fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.written = true;
+        self.buffer.as_mut_ptr()
+    }
+
+    /// Alignment in bytes of the start of the mapped region
+    ///
+    /// This is the largest power of two the address of [`Self::buffer()`] is a multiple of.
+    /// Rows are only aligned as well, if the [stride](Self::stride()) is a multiple of the
+    /// required alignment, see [`Self::aligned_rows()`].
+    pub fn ptr_alignment(&self) -> usize {
+        1 << (self.buffer.as_ptr() as usize).trailing_zeros()
+    }
+
+    /// Iterate over the rows of the mapped region, if every row is aligned to `N` bytes
+    ///
+    /// Each yielded row starts at an address that is a multiple of `N` and contains the
+    /// pixel data of the row excluding the stride padding. Returns `None` if `N` is zero
+    /// or the mapping or the stride isn't a multiple of `N`, in which case callers should
+    /// fall back to unaligned processing, and for [planar](crate::planar_info()) formats.
+    pub fn aligned_rows<const N: usize>(&self) -> Option<impl Iterator<Item = &[u8]> + '_> {
+        if N == 0
+            || self.stride == 0
+            || self.ptr_alignment() % N != 0
+            || self.stride as usize % N != 0
+        {
+            return None;
+        }
+        let row_bytes = self.row_bytes().ok()?;
+        Some(
+            self.buffer
+                .chunks(self.stride as usize)
+                .take(self.height as usize)
+                .map(move |row| &row[..row_bytes]),
+        )
+    }
+
+    /// Copy tightly packed rows of `row_bytes` bytes each into the strided mapped region
+    ///
+    /// Row `n` of `data` is copied to the start of row `n` of the mapping, leaving the
+    /// remaining bytes of each row untouched. Fails with [`io::ErrorKind::InvalidInput`]
+    /// if `row_bytes` is zero or larger than the stride, or if `data` isn't a multiple of
+    /// `row_bytes` or contains more rows than the mapped region, and with
+    /// [`io::ErrorKind::PermissionDenied`] for read-only mappings.
+    pub fn write_packed(&mut self, data: &[u8], row_bytes: usize) -> IoResult<()> {
+        if let BORef::Ref(_) = self.bo {
+            return Err(IoError::new(
+                io::ErrorKind::PermissionDenied,
+                "buffer object is mapped read-only",
+            ));
+        }
+        if row_bytes == 0 || row_bytes > self.stride as usize {
+            return Err(IoError::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "row size of {} bytes doesn't fit the stride of {} bytes",
+                    row_bytes, self.stride
+                ),
+            ));
+        }
+        if data.len() % row_bytes != 0 || data.len() / row_bytes > self.height as usize {
+            return Err(IoError::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} bytes are not a multiple of {} bytes per row or exceed {} rows",
+                    data.len(),
+                    row_bytes,
+                    self.height
+                ),
+            ));
+        }
+        self.written = true;
+        for (src, dst) in data
+            .chunks(row_bytes)
+            .zip(self.buffer.chunks_mut(self.stride as usize))
+        {
+            dst[..row_bytes].copy_from_slice(src);
+        }
+        Ok(())
+    }
+
+    /// Get the bytes of the pixel at `x`, `y`, relative to the mapped region
+    ///
+    /// Returns `None` if the position is outside of the mapped region or the format
+    /// doesn't use a whole number of bytes per pixel, e.g. for planar formats.
+    pub fn get(&self, x: u32, y: u32) -> Option<&[u8]> {
+        let range = self.pixel_range(x, y)?;
+        self.buffer.get(range)
+    }
+
+    /// Get mutable access to the bytes of the pixel at `x`, `y`, relative to the mapped region
+    ///
+    /// See [`Self::get()`]. Requires a mapping with write access, e.g. from
+    /// [`BufferObject::map_mut()`].
+    pub fn get_mut(&mut self, x: u32, y: u32) -> Option<&mut [u8]> {
+        let range = self.pixel_range(x, y)?;
+        self.written = true;
+        self.buffer.get_mut(range)
+    }
+
+    /// Read the pixel at `x`, `y` of a format with 10-bit color channels
+    ///
+    /// Returns red, green, blue and alpha as unpacked by [`unpack_rgb10()`](crate::unpack_rgb10()),
+    /// or `None` if the position is outside of the mapped region or the format doesn't
+    /// have 10-bit color channels, e.g. [`Format::Argb2101010`].
+    pub fn get_rgb10(&self, x: u32, y: u32) -> Option<[u16; 4]> {
+        let pixel = self.get(x, y)?.try_into().ok()?;
+        crate::unpack_rgb10(self.format(), u32::from_le_bytes(pixel))
+    }
+
+    /// Write the pixel at `x`, `y` of a format with 10-bit color channels
+    ///
+    /// Packs `rgba` with [`pack_rgb10()`](crate::pack_rgb10()). Returns `false` if the
+    /// position is outside of the mapped region or the format doesn't have 10-bit color
+    /// channels. Like [`Self::get_mut()`] this is only reachable through mutable mappings.
+    pub fn set_rgb10(&mut self, x: u32, y: u32, rgba: [u16; 4]) -> bool {
+        let Some(pixel) = crate::pack_rgb10(self.format(), rgba) else {
+            return false;
+        };
+        match self.get_mut(x, y) {
+            Some(bytes) if bytes.len() == 4 => {
+                bytes.copy_from_slice(&pixel.to_le_bytes());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn pixel_range(&self, x: u32, y: u32) -> Option<std::ops::Range<usize>> {
+        let bpp = BufferObject::<T>::bpp(self);
+        if x >= self.width || y >= self.height || bpp == 0 || bpp % 8 != 0 {
+            return None;
+        }
+        if self.plane_count() != 1 {
+            return None;
+        }
+        let bytes = bpp as usize / 8;
+        let start = y as usize * self.stride as usize + x as usize * bytes;
+        Some(start..start + bytes)
+    }
+
+    /// Iterate over the rows of the mapped region as slices of exactly `row_len` bytes
+    ///
+    /// Like [`slice::chunks_exact()`] every item has the same length, but the stride
+    /// padding following the first `row_len` bytes of every row is skipped. Usually
+    /// `row_len` is the number of bytes of pixel data in a row, i.e. `width * bpp / 8`.
+    ///
+    /// Yields nothing if `row_len` exceeds the [stride](Self::stride()).
+    pub fn rows_exact(&self, row_len: usize) -> impl Iterator<Item = &[u8]> {
+        let stride = self.stride as usize;
+        let rows = if row_len <= stride {
+            self.height as usize
+        } else {
+            0
+        };
         self.buffer
+            .chunks_exact(stride.max(1))
+            .take(rows)
+            .map(move |row| &row[..row_len])
+    }
+
+    /// Split the mapped region into two disjoint mutable views before row `at`
+    ///
+    /// The first view contains the rows `0..at`, the second the rows `at..height`. The
+    /// views can be split further and processed on different threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is larger than the [height](Self::height()) of the mapped region.
+    pub fn split_rows(&mut self, at: u32) -> (RowsMut<'_>, RowsMut<'_>) {
+        self.written = true;
+        RowsMut {
+            buffer: self.buffer,
+            stride: self.stride as usize,
+            height: self.height,
+        }
+        .split_at(at)
+    }
+
+    // Number of bytes of pixel data in a row, excluding the stride padding. The bpp of
+    // planar formats describes the whole buffer rather than the rows of a plane, so their
+    // rows can't be told apart from the padding.
+    fn row_bytes(&self) -> IoResult<usize> {
+        if crate::planar_info(self.format()).is_some() {
+            return Err(IoError::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "packed rows of planar format {:?} are unsupported",
+                    self.format()
+                ),
+            ));
+        }
+        let bits = self.width as usize * BufferObject::<T>::bpp(self) as usize;
+        Ok(((bits + 7) / 8).min(self.stride as usize))
+    }
+
+    // Translate the logical (tightly packed) stream position into the strided buffer,
+    // returning the buffer offset and the remaining bytes of the current row
+    fn cursor(&self) -> IoResult<Option<(usize, usize)>> {
+        let row_bytes = self.row_bytes()?;
+        if row_bytes == 0 {
+            return Ok(None);
+        }
+        let row = self.pos / row_bytes;
+        if row >= self.height as usize {
+            return Ok(None);
+        }
+        let col = self.pos % row_bytes;
+        Ok(Some((row * self.stride as usize + col, row_bytes - col)))
+    }
+}
+
+/// Mutable view of consecutive rows of a [`MappedBufferObject`]
+///
+/// Created by [`MappedBufferObject::split_rows()`].
+#[derive(Debug)]
+pub struct RowsMut<'a> {
+    buffer: &'a mut [u8],
+    stride: usize,
+    height: u32,
+}
+
+impl<'a> RowsMut<'a> {
+    /// Number of rows in this view
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Stride of the rows in bytes, including padding
+    pub fn stride(&self) -> u32 {
+        self.stride as u32
+    }
+
+    /// Mutable access to row `row` of this view, including the stride padding
+    ///
+    /// Returns `None` if `row` is out of bounds.
+    pub fn row_mut(&mut self, row: u32) -> Option<&mut [u8]> {
+        if row >= self.height {
+            return None;
+        }
+        let start = row as usize * self.stride;
+        let end = (start + self.stride).min(self.buffer.len());
+        Some(&mut self.buffer[start..end])
+    }
+
+    /// Iterate over the rows of this view, including the stride padding
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut [u8]> + '_ {
+        self.buffer
+            .chunks_mut(self.stride.max(1))
+            .take(self.height as usize)
+    }
+
+    /// Split this view into two disjoint views before row `at`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is larger than the [height](Self::height()) of this view.
+    pub fn split_at(self, at: u32) -> (RowsMut<'a>, RowsMut<'a>) {
+        assert!(at <= self.height, "row {} out of bounds", at);
+        let mid = (at as usize * self.stride).min(self.buffer.len());
+        let (first, second) = self.buffer.split_at_mut(mid);
+        (
+            RowsMut {
+                buffer: first,
+                stride: self.stride,
+                height: at,
+            },
+            RowsMut {
+                buffer: second,
+                stride: self.stride,
+                height: self.height - at,
+            },
+        )
+    }
+}
+
+/// Reads the mapped region as a tightly packed byte stream, skipping the stride padding.
+///
+/// Reading and writing share the same cursor, which starts at the top left corner of the mapped region.
+/// Fails with [`io::ErrorKind::InvalidInput`] for [planar](crate::planar_info()) formats.
+impl<'a, T: 'static> io::Read for MappedBufferObject<'a, T> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let mut read = 0;
+        while read < buf.len() {
+            let Some((offset, remaining)) = self.cursor()? else {
+                break;
+            };
+            let len = remaining.min(buf.len() - read);
+            buf[read..read + len].copy_from_slice(&self.buffer[offset..offset + len]);
+            read += len;
+            self.pos += len;
+        }
+        Ok(read)
+    }
+}
+
+/// Writes a tightly packed byte stream into the mapped region, skipping the stride padding.
+///
+/// Writing fails for buffers mapped read-only via [`BufferObject::map()`].
+/// Reading and writing share the same cursor, which starts at the top left corner of the mapped region.
+/// Fails with [`io::ErrorKind::InvalidInput`] for [planar](crate::planar_info()) formats.
+impl<'a, T: 'static> io::Write for MappedBufferObject<'a, T> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        if let BORef::Ref(_) = self.bo {
+            return Err(IoError::new(
+                io::ErrorKind::PermissionDenied,
+                "buffer object is mapped read-only",
+            ));
+        }
+        let mut written = 0;
+        while written < buf.len() {
+            let Some((offset, remaining)) = self.cursor()? else {
+                break;
+            };
+            let len = remaining.min(buf.len() - written);
+            self.buffer[offset..offset + len].copy_from_slice(&buf[written..written + len]);
+            written += len;
+            self.pos += len;
+            self.written = true;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
     }
 }
 
@@ -158,8 +720,11 @@ impl<'a, T: 'static> Deref for MappedBufferObject<'a, T> {
 
 impl<'a, T: 'static> DerefMut for MappedBufferObject<'a, T> {
     fn deref_mut(&mut self) -> &mut BufferObject<T> {
+        self.written = true;
         match &mut self.bo {
-            BORef::Ref(_) => unreachable!(),
+            // Read-only mappings are only handed out behind shared references, either to
+            // the callback of `BufferObject::map` or wrapped in a `MappedBufferObjectRef`
+            BORef::Ref(_) => unreachable!("read-only mapping accessed mutably"),
             BORef::Mut(bo) => bo,
         }
     }
@@ -167,14 +732,152 @@ impl<'a, T: 'static> DerefMut for MappedBufferObject<'a, T> {
 
 impl<'a, T: 'static> Drop for MappedBufferObject<'a, T> {
     fn drop(&mut self) {
-        let ffi = match &self.bo {
-            BORef::Ref(bo) => &bo.ffi,
-            BORef::Mut(bo) => &bo.ffi,
+        let ffi = match &mut self.bo {
+            BORef::Ref(bo) => {
+                #[cfg(debug_assertions)]
+                bo.untrack_read_map((self.x, self.y, self.width, self.height));
+                &bo.ffi
+            }
+            BORef::Mut(bo) => {
+                if self.written {
+                    bo.mark_cpu_written();
+                }
+                &bo.ffi
+            }
         };
         unsafe { ffi::gbm_bo_unmap(**ffi, self.data) }
     }
 }
 
+/// A region of a buffer object mapped for cpu read access
+///
+/// Returned by [`BufferObject::map_guard()`]. Dereferences to the read accessors of
+/// [`MappedBufferObject`], the region is unmapped once this is dropped.
+pub struct MappedBufferObjectRef<'a, T: 'static>(MappedBufferObject<'a, T>);
+
+impl<'a, T: 'static> fmt::Debug for MappedBufferObjectRef<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<'a, T: 'static> Deref for MappedBufferObjectRef<'a, T> {
+    type Target = MappedBufferObject<'a, T>;
+    fn deref(&self) -> &MappedBufferObject<'a, T> {
+        &self.0
+    }
+}
+
+/// Reads the mapped region as a tightly packed byte stream, see [`MappedBufferObject`].
+impl<'a, T: 'static> io::Read for MappedBufferObjectRef<'a, T> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        self.0.read(buf)
+    }
+}
+
+/// A view of the persistent mapping of a buffer object
+///
+/// Returned by [`BufferObject::persistent_map()`]. Dropping the view keeps the buffer
+/// object mapped, consume it with [`Self::flush()`] or [`Self::unmap()`] to write back
+/// or unmap the mapping. As the view borrows the buffer object mutably, the mapping can't
+/// be changed by other means while the view is alive.
+pub struct PersistentMapGuard<'a, T: 'static> {
+    bo: &'a mut BufferObject<T>,
+    ptr: *mut u8,
+    stride: u32,
+    // Set once mutable access to the mapped memory was handed out
+    written: bool,
+}
+
+impl<'a, T: 'static> fmt::Debug for PersistentMapGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PersistentMapGuard")
+            .field("buffer", &self.bo)
+            .field("written", &self.written)
+            .finish()
+    }
+}
+
+impl<'a, T: 'static> PersistentMapGuard<'a, T> {
+    /// Get the stride of the mapping
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// The width of the buffer object
+    pub fn width(&self) -> u32 {
+        self.bo.width()
+    }
+
+    /// The height of the buffer object
+    pub fn height(&self) -> u32 {
+        self.bo.height()
+    }
+
+    /// Access to the mapped memory, `stride * height` bytes
+    pub fn buffer(&self) -> &[u8] {
+        let len = (self.stride * self.bo.height()) as usize;
+        unsafe { slice::from_raw_parts(self.ptr, len) }
+    }
+
+    /// Mutable access to the mapped memory, `stride * height` bytes
+    pub fn buffer_mut(&mut self) -> &mut [u8] {
+        self.written = true;
+        let len = (self.stride * self.bo.height()) as usize;
+        unsafe { slice::from_raw_parts_mut(self.ptr, len) }
+    }
+
+    /// Whether the mapped memory might have been written to through this view,
+    /// see [`MappedBufferObject::was_written()`]
+    pub fn was_written(&self) -> bool {
+        self.written
+    }
+
+    /// Push writes through the mapping to the buffer object, keeping it mapped
+    ///
+    /// Buffer objects, that aren't linear, are unmapped and mapped again, which writes back
+    /// the staging copy on most drivers. This is a no-op for linear buffer objects or if
+    /// the mapping wasn't written to since it was last flushed. If mapping the buffer
+    /// object again fails, the error is returned and the buffer object is left unmapped.
+    pub fn flush(mut self) -> IoResult<()> {
+        self.record_writes();
+        let dirty = self
+            .bo
+            .persistent_mapping
+            .as_ref()
+            .map_or(false, |mapping| mapping.dirty);
+        if !dirty || self.bo.is_linear() {
+            return Ok(());
+        }
+        self.bo.persistent_mapping = None;
+        let (width, height) = (self.bo.width(), self.bo.height());
+        self.bo.persistent_mapping =
+            Some(unsafe { PersistentMapping::new(*self.bo.ffi, width, height)? });
+        Ok(())
+    }
+
+    /// Unmap the persistent mapping, writing it back to the buffer object
+    pub fn unmap(mut self) {
+        self.record_writes();
+        self.bo.persistent_mapping = None;
+    }
+
+    fn record_writes(&mut self) {
+        if std::mem::take(&mut self.written) {
+            self.bo.mark_cpu_written();
+            if let Some(mapping) = self.bo.persistent_mapping.as_mut() {
+                mapping.dirty = true;
+            }
+        }
+    }
+}
+
+impl<'a, T: 'static> Drop for PersistentMapGuard<'a, T> {
+    fn drop(&mut self) {
+        self.record_writes();
+    }
+}
+
 unsafe extern "C" fn destroy<T: 'static>(_: *mut ffi::gbm_bo, ptr: *mut ::libc::c_void) {
     let ptr = ptr as *mut T;
     if !ptr.is_null() {
@@ -209,19 +912,61 @@ impl<T: 'static> BufferObject<T> {
             .expect("libgbm returned invalid buffer format")
     }
 
+    /// Get the order of the color channels in memory, see [`ChannelOrder::from_format()`]
+    pub fn channel_order(&self) -> Option<ChannelOrder> {
+        ChannelOrder::from_format(self.format())
+    }
+
     /// Get the bits per pixel of the buffer object
     pub fn bpp(&self) -> u32 {
         unsafe { ffi::gbm_bo_get_bpp(*self.ffi) }
     }
 
+    /// Get the bits per sample of a plane of the buffer object
+    ///
+    /// [`Self::bpp()`] describes the whole buffer, which is meaningless for planar
+    /// formats. For them this returns the size of a sample of the plane as described by
+    /// [`planar_info()`](crate::planar_info()), e.g. `8` for the luma and `16` for the
+    /// interleaved chroma plane of [`Format::Nv12`], where one chroma sample covers
+    /// 2x2 pixels. For single plane formats this returns [`Self::bpp()`] for plane `0`.
+    ///
+    /// Returns `0` for planes not described by the format, e.g. auxiliary planes of
+    /// compressed layouts.
+    pub fn bpp_for_plane(&self, plane: i32) -> u32 {
+        let Ok(index) = usize::try_from(plane) else {
+            return 0;
+        };
+        match crate::planar_info(self.format()) {
+            Some(info) => info
+                .planes
+                .get(index)
+                .map_or(0, |plane| plane.bytes_per_block * 8),
+            None if index == 0 => self.bpp(),
+            None => 0,
+        }
+    }
+
     /// Get the offset for a plane of the buffer object
     pub fn offset(&self, plane: i32) -> u32 {
         unsafe { ffi::gbm_bo_get_offset(*self.ffi, plane) }
     }
 
     /// Get the plane count of the buffer object
+    ///
+    /// The value is queried once and cached afterwards.
     pub fn plane_count(&self) -> u32 {
-        unsafe { ffi::gbm_bo_get_plane_count(*self.ffi) as u32 }
+        // The layout of a `gbm_bo`, including its number of planes, is fixed at allocation
+        // or import time, so the plane count never changes for the lifetime of the bo.
+        let cached = self.plane_count.load(Ordering::Relaxed);
+        if cached != UNKNOWN_PLANE_COUNT {
+            debug_assert_eq!(cached, unsafe {
+                ffi::gbm_bo_get_plane_count(*self.ffi) as u32
+            });
+            return cached;
+        }
+        let count = unsafe { ffi::gbm_bo_get_plane_count(*self.ffi) as u32 };
+        self.plane_count.store(count, Ordering::Relaxed);
+        count
     }
 
     /// Get the modifier of the buffer object
@@ -229,14 +974,89 @@ impl<T: 'static> BufferObject<T> {
         Modifier::from(unsafe { ffi::gbm_bo_get_modifier(*self.ffi) })
     }
 
+    /// Get the list of modifiers requested when allocating the buffer object
+    ///
+    /// Together with [`Self::modifier()`], the modifier chosen by the driver, this helps
+    /// debugging modifier negotiation. Returns `None` for buffer objects not allocated
+    /// with an explicit list of modifiers, e.g. imported buffers.
+    pub fn requested_modifiers(&self) -> Option<&[Modifier]> {
+        self.requested_modifiers.as_deref()
+    }
+
+    /// Whether the driver ignored the [requested modifiers](Self::requested_modifiers())
+    ///
+    /// Some drivers silently ignore the list of modifiers and allocate a buffer object with
+    /// an implicit layout ([`Modifier::Invalid`]), even though explicit modifiers were
+    /// requested, e.g. [`Modifier::Linear`] for scanout, which may cause corruption when
+    /// the buffer is displayed. This returns `true` in that case and `false` for buffer
+    /// objects not allocated with an explicit list of modifiers.
+    ///
+    /// In debug builds a warning is logged on allocation, if the modifiers were ignored.
+    pub fn modifiers_ignored(&self) -> bool {
+        self.requested_modifiers
+            .as_deref()
+            .map_or(false, |requested| {
+                self.modifier() == Modifier::Invalid
+                    && requested
+                        .iter()
+                        .any(|modifier| *modifier != Modifier::Invalid)
+            })
+    }
+
+    /// Get the usage flags the buffer object was created or imported with
+    ///
+    /// Returns `None` if the flags are unknown, e.g. for buffers obtained from a
+    /// [`Surface`](crate::Surface) or allocated without explicit flags.
+    pub fn usage(&self) -> Option<BufferObjectFlags> {
+        self.usage
+    }
+
+    /// Guess whether the buffer object can be mapped for cpu access
+    ///
+    /// This is a heuristic based on the modifier and the recorded [usage flags](Self::usage()),
+    /// not a guarantee. Linear buffers are considered mappable, as are buffers with an
+    /// implicit (`Modifier::Invalid`) layout, since drivers usually map those through a
+    /// staging copy. Protected buffers and buffers using a vendor specific (tiled or
+    /// compressed) modifier are considered unmappable.
+    pub fn is_mappable(&self) -> bool {
+        if let Some(usage) = self.usage {
+            if usage.contains(BufferObjectFlags::PROTECTED) {
+                return false;
+            }
+            if usage.contains(BufferObjectFlags::LINEAR) {
+                return true;
+            }
+        }
+        matches!(self.modifier(), Modifier::Linear | Modifier::Invalid)
+    }
+
+    /// Check whether the offsets of all planes are aligned to the page size
+    ///
+    /// KMS drivers often require planes of scanout buffers to start at a page boundary
+    /// and reject framebuffers for other buffers. This is a best-effort check of a common
+    /// cause of failing to scan out imported buffers, passing it doesn't guarantee
+    /// that the buffer can be scanned out.
+    pub fn is_scanout_aligned(&self) -> bool {
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        let page_size = if page_size > 0 {
+            page_size as u32
+        } else {
+            4096
+        };
+        self.planes().all(|plane| plane.offset % page_size == 0)
+    }
+
     /// Get a DMA-BUF file descriptor for the buffer object
     ///
     /// This function creates a DMA-BUF (also known as PRIME) file descriptor
     /// handle for the buffer object.  Each call to [`Self::fd()`] returns a new
     /// file descriptor and the caller is responsible for closing the file
     /// descriptor.
+    ///
+    /// Fails if the limit set with [`set_fd_export_limit()`](crate::set_fd_export_limit())
+    /// is reached.
     pub fn fd(&self) -> Result<OwnedFd, InvalidFdError> {
-        unsafe {
+        crate::fd_limit::export_fd(|| unsafe {
             let fd = ffi::gbm_bo_get_fd(*self.ffi);
 
             if fd == -1 {
@@ -244,12 +1064,121 @@ impl<T: 'static> BufferObject<T> {
             }
 
             Ok(OwnedFd::from_raw_fd(fd))
+        })
+    }
+
+    /// Borrow a cached DMA-BUF file descriptor for the buffer object
+    ///
+    /// Unlike [`Self::fd()`] the file descriptor is only exported on the first call and
+    /// kept open for the lifetime of the buffer object. For buffers with multiple planes
+    /// this is the file descriptor of the first plane, use [`Self::fd_for_plane()`]
+    /// for the other planes.
+    ///
+    /// `BufferObject` deliberately doesn't implement [`AsFd`],
+    /// as exporting the file descriptor may fail, which `AsFd` can't report.
+    pub fn try_as_fd(&self) -> Result<BorrowedFd<'_>, InvalidFdError> {
+        let mut cached = self.cached_fd.lock().unwrap();
+        let fd = match &*cached {
+            Some(fd) => fd.as_raw_fd(),
+            None => {
+                let fd = self.fd()?;
+                let raw_fd = fd.as_raw_fd();
+                *cached = Some(fd);
+                raw_fd
+            }
+        };
+        // The cached file descriptor is only closed once the buffer object is dropped
+        Ok(unsafe { BorrowedFd::borrow_raw(fd) })
+    }
+
+    /// Import the buffer object on another device, sharing the underlying memory
+    ///
+    /// This exports every plane as DMA-BUF and imports them with the same dimensions,
+    /// format, modifier and plane layout on `device`, e.g. to scan out a buffer rendered
+    /// on another GPU. The exported file descriptors are closed once the import finished.
+    ///
+    /// The destination device has to support the modifier of the buffer object. Buffers
+    /// using an implicit layout (`Modifier::Invalid`) are only guaranteed to be
+    /// interpreted correctly if both devices are driven by the same driver.
+    pub fn clone_to_device<X: AsFd, U: 'static>(
+        &self,
+        device: &Device<X>,
+        usage: BufferObjectFlags,
+    ) -> IoResult<BufferObject<U>> {
+        let planes = self.planes().collect::<Vec<_>>();
+        let fds = planes
+            .iter()
+            .map(|plane| self.fd_for_plane(plane.index))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut builder = DmabufImportBuilder::new()
+            .dimensions(self.width(), self.height())
+            .format(self.format())
+            .modifier(self.modifier());
+        for (plane, fd) in planes.iter().zip(&fds) {
+            builder = builder.plane(fd.as_fd(), plane.offset, plane.stride);
+        }
+        device.import(builder.build()?, usage)
+    }
+
+    /// Export the buffer object as DMA-BUF file descriptor, consuming it
+    ///
+    /// The DMA-BUF keeps the underlying memory alive after the buffer object is dropped,
+    /// which makes this useful to hand off a buffer. This is only supported for
+    /// single-plane buffers, for buffers with multiple planes an error of kind
+    /// [`io::ErrorKind::InvalidInput`] is returned and the planes have to be exported
+    /// individually using [`Self::fd_for_plane()`].
+    pub fn into_fd(self) -> IoResult<OwnedFd> {
+        let planes = self.plane_count();
+        if planes > 1 {
+            return Err(IoError::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "buffer object has {} planes, export them with `fd_for_plane`",
+                    planes
+                ),
+            ));
+        }
+        if let Some(fd) = self.cached_fd.lock().unwrap().take() {
+            return Ok(fd);
+        }
+        Ok(self.fd()?)
+    }
+
+    /// Check whether pending GPU writes to the buffer object have finished
+    ///
+    /// This polls an exported DMA-BUF file descriptor of the buffer object for readability.
+    /// With implicit synchronization the kernel attaches the fences of pending rendering
+    /// to the DMA-BUF, and the file descriptor only becomes readable once all writing
+    /// fences have signaled. Returns `Ok(false)` if the buffer is still being written
+    /// to after `timeout` elapsed.
+    ///
+    /// Buffers from drivers relying on explicit synchronization will usually appear
+    /// readable immediately, as no implicit fences are attached.
+    pub fn poll_readable(&self, timeout: Duration) -> IoResult<bool> {
+        let fd = self.fd()?;
+        let mut pollfd = libc::pollfd {
+            fd: fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        loop {
+            match unsafe { libc::poll(&mut pollfd, 1, timeout) } {
+                -1 => {
+                    let err = IoError::last_os_error();
+                    if err.kind() != io::ErrorKind::Interrupted {
+                        return Err(err);
+                    }
+                }
+                0 => return Ok(false),
+                _ => return Ok(pollfd.revents & libc::POLLIN != 0),
+            }
         }
     }
 
     /// Get the file descriptor of the gbm device of this buffer object
-    pub fn device_fd(&self) -> BorrowedFd {
-        unsafe { BorrowedFd::borrow_raw(ffi::gbm_device_get_fd(*self._device)) }
+    pub fn device_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.device_fd) }
     }
 
     /// Get the handle of the buffer object
@@ -266,8 +1195,16 @@ impl<T: 'static> BufferObject<T> {
     /// handle for a plane of the buffer object. Each call to [`Self::fd_for_plane()`]
     /// returns a new file descriptor and the caller is responsible for closing
     /// the file descriptor.
+    ///
+    /// Fails if the loaded libgbm doesn't provide `gbm_bo_get_fd_for_plane`
+    /// (added in Mesa 21.1), see [`Device::has_symbol()`](crate::Device::has_symbol()),
+    /// or if the limit set with [`set_fd_export_limit()`](crate::set_fd_export_limit())
+    /// is reached.
     pub fn fd_for_plane(&self, plane: i32) -> Result<OwnedFd, InvalidFdError> {
-        unsafe {
+        if !crate::symbols::BO_GET_FD_FOR_PLANE.is_present() {
+            return Err(InvalidFdError);
+        }
+        crate::fd_limit::export_fd(|| unsafe {
             let fd = ffi::gbm_bo_get_fd_for_plane(*self.ffi, plane);
 
             if fd == -1 {
@@ -275,7 +1212,7 @@ impl<T: 'static> BufferObject<T> {
             }
 
             Ok(OwnedFd::from_raw_fd(fd))
-        }
+        })
     }
 
     /// Get the handle of a plane of the buffer object
@@ -286,47 +1223,91 @@ impl<T: 'static> BufferObject<T> {
         unsafe { ffi::gbm_bo_get_handle_for_plane(*self.ffi, plane) }
     }
 
+    /// Iterate over the layout of all planes of the buffer object
+    ///
+    /// The iterator yields exactly [`Self::plane_count()`] entries. A DMA-BUF file
+    /// descriptor for a plane can be exported with [`Self::fd_for_plane()`] using
+    /// the [`PlaneInfo::index`].
+    pub fn planes(&self) -> Planes<'_, T> {
+        Planes {
+            bo: self,
+            next: 0,
+            count: self.plane_count() as i32,
+        }
+    }
+
+    /// Collect the layout of all planes of the buffer object
+    ///
+    /// Unlike the fixed size arrays of the `drm::buffer::PlanarBuffer` implementation,
+    /// which pad missing planes with `0`, this contains exactly [`Self::plane_count()`]
+    /// entries, so a plane with an offset of `0` can't be confused with a missing one.
+    pub fn active_planes(&self) -> SmallVec<[PlaneInfo; 4]> {
+        self.planes().collect()
+    }
+
     /// Map a region of a GBM buffer object for cpu access
     ///
     /// This function maps a region of a GBM bo for cpu read access.
-    pub fn map<'a, F, S>(&'a self, x: u32, y: u32, width: u32, height: u32, f: F) -> IoResult<S>
+    ///
+    /// In debug builds a warning is logged, if the buffer object is mapped for reading
+    /// after it was written to by the cpu (through [`Self::map_mut()`] or [`Self::write()`])
+    /// without synchronizing the DMA-BUF in between, see `BufferObject::dma_buf_sync_start`
+    /// of the `dma-buf-sync` feature.
+    ///
+    /// Failures are classified as [`MapError`], which converts into an [`io::Error`].
+    pub fn map<'a, F, S>(
+        &'a self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        f: F,
+    ) -> Result<S, MapError>
     where
         F: FnOnce(&MappedBufferObject<'a, T>) -> S,
     {
-        unsafe {
-            let mut data: *mut ::libc::c_void = ptr::null_mut();
-            let mut stride = 0;
-            let ptr = ffi::gbm_bo_map(
-                *self.ffi,
-                x,
-                y,
-                width,
-                height,
-                ffi::gbm_bo_transfer_flags::GBM_BO_TRANSFER_READ as u32,
-                &mut stride as *mut _,
-                &mut data as *mut _,
-            );
+        let mapping = self.map_guard(x, y, width, height)?;
+        Ok(f(&mapping.0))
+    }
 
-            if ptr.is_null() {
-                Err(IoError::last_os_error())
-            } else {
-                Ok(f(&MappedBufferObject {
-                    bo: BORef::Ref(self),
-                    buffer: slice::from_raw_parts_mut(ptr as *mut _, (height * stride) as usize),
-                    data,
-                    stride,
-                    height,
-                    width,
-                    x,
-                    y,
-                }))
-            }
-        }
+    /// Map a region of a GBM buffer object for cpu read access, expanded to whole tiles
+    ///
+    /// Drivers have to detile buffers with a tiled layout to map them, which can be slow
+    /// or fail for regions not covering whole tiles. This expands the region outwards to
+    /// the tile boundaries of the [modifier](Self::modifier()) of the buffer object,
+    /// clamped to the size of the buffer object, and maps the expanded region as
+    /// [`Self::map()`] does. The [`MappedBufferObject::x()`], [`MappedBufferObject::y()`],
+    /// [`MappedBufferObject::width()`] and [`MappedBufferObject::height()`] of the mapping
+    /// passed to `f` describe the expanded region.
+    ///
+    /// The tile sizes of the common Intel, Samsung, Vivante, Nvidia Tegra and Allwinner
+    /// layouts are known. Regions of buffer objects with a linear, implicit or other
+    /// layout are mapped as requested.
+    pub fn map_aligned<'a, F, S>(
+        &'a self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        f: F,
+    ) -> Result<S, MapError>
+    where
+        F: FnOnce(&MappedBufferObject<'a, T>) -> S,
+    {
+        let (x, y, width, height) = align_to_tiles(
+            (x, y, width, height),
+            (self.width(), self.height()),
+            tile_size(self.modifier(), self.bpp()),
+        )
+        .ok_or(MapError::InvalidRegion)?;
+        self.map(x, y, width, height, f)
     }
 
     /// Map a region of a GBM buffer object for cpu access
     ///
     /// This function maps a region of a GBM bo for cpu read/write access.
+    ///
+    /// Failures are classified as [`MapError`], which converts into an [`io::Error`].
     pub fn map_mut<'a, F, S>(
         &'a mut self,
         x: u32,
@@ -334,41 +1315,157 @@ impl<T: 'static> BufferObject<T> {
         width: u32,
         height: u32,
         f: F,
-    ) -> IoResult<S>
+    ) -> Result<S, MapError>
     where
         F: FnOnce(&mut MappedBufferObject<'a, T>) -> S,
     {
-        unsafe {
-            let mut data: *mut ::libc::c_void = ptr::null_mut();
-            let mut stride = 0;
-            let ptr = ffi::gbm_bo_map(
+        let mut mapping = self.map_mut_guard(x, y, width, height)?;
+        Ok(f(&mut mapping))
+    }
+
+    /// Map a region of a GBM buffer object for cpu read access, returning the mapping
+    ///
+    /// Unlike [`Self::map()`] the mapping is returned directly and the region is unmapped
+    /// once the returned [`MappedBufferObjectRef`] is dropped. It only provides read
+    /// access to the mapped region and the buffer object.
+    ///
+    /// Multiple regions can be mapped for reading at the same time. Some drivers corrupt
+    /// the data of overlapping maps or require them to be unmapped in reverse order, so
+    /// in debug builds a warning is logged, if the region overlaps another region still
+    /// mapped through the same handle. Handles created by [`Self::dup()`] are tracked
+    /// separately.
+    pub fn map_guard(
+        &self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<MappedBufferObjectRef<'_, T>, MapError> {
+        self.check_cpu_access()?;
+        self.check_read_hazard();
+        let mapping = unsafe {
+            Self::map_region(
                 *self.ffi,
+                BORef::Ref(self),
                 x,
                 y,
                 width,
                 height,
-                ffi::gbm_bo_transfer_flags::GBM_BO_TRANSFER_READ_WRITE as u32,
-                &mut stride as *mut _,
-                &mut data as *mut _,
-            );
+                ffi::gbm_bo_transfer_flags::GBM_BO_TRANSFER_READ,
+            )
+        }?;
+        #[cfg(debug_assertions)]
+        self.track_read_map((x, y, width, height));
+        Ok(MappedBufferObjectRef(mapping))
+    }
 
-            if ptr.is_null() {
-                Err(IoError::last_os_error())
-            } else {
-                Ok(f(&mut MappedBufferObject {
-                    bo: BORef::Mut(self),
-                    buffer: slice::from_raw_parts_mut(ptr as *mut _, (height * stride) as usize),
-                    data,
-                    stride,
-                    height,
-                    width,
-                    x,
-                    y,
-                }))
-            }
+    /// Map a region of a GBM buffer object for cpu read/write access, returning the mapping
+    ///
+    /// Unlike [`Self::map_mut()`] the mapping is returned directly and the region is
+    /// unmapped once the returned [`MappedBufferObject`] is dropped.
+    pub fn map_mut_guard(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<MappedBufferObject<'_, T>, MapError> {
+        self.check_cpu_access()?;
+        let ffi = *self.ffi;
+        unsafe {
+            Self::map_region(
+                ffi,
+                BORef::Mut(self),
+                x,
+                y,
+                width,
+                height,
+                ffi::gbm_bo_transfer_flags::GBM_BO_TRANSFER_READ_WRITE,
+            )
         }
     }
 
+    // Maps the region of `ffi`, which has to be the buffer object referenced by `bo`
+    unsafe fn map_region<'a>(
+        ffi: *mut ffi::gbm_bo,
+        bo: BORef<'a, T>,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        flags: ffi::gbm_bo_transfer_flags::Type,
+    ) -> Result<MappedBufferObject<'a, T>, MapError> {
+        let (bo_width, bo_height) = (ffi::gbm_bo_get_width(ffi), ffi::gbm_bo_get_height(ffi));
+        if x.checked_add(width).map_or(true, |right| right > bo_width)
+            || y.checked_add(height)
+                .map_or(true, |bottom| bottom > bo_height)
+        {
+            return Err(MapError::InvalidRegion);
+        }
+
+        let mut data: *mut ::libc::c_void = ptr::null_mut();
+        let mut stride = 0;
+        let ptr = ffi::gbm_bo_map(
+            ffi,
+            x,
+            y,
+            width,
+            height,
+            flags as u32,
+            &mut stride as *mut _,
+            &mut data as *mut _,
+        );
+
+        if ptr.is_null() {
+            Err(MapError::from_os_error(IoError::last_os_error()))
+        } else {
+            Ok(MappedBufferObject {
+                bo,
+                buffer: slice::from_raw_parts_mut(ptr as *mut _, (height * stride) as usize),
+                data,
+                stride,
+                height,
+                width,
+                x,
+                y,
+                pos: 0,
+                written: false,
+            })
+        }
+    }
+
+    /// Map the whole buffer object for cpu read/write access, keeping it mapped across calls
+    ///
+    /// The first call maps the buffer object, following calls return a view of the same
+    /// mapping, avoiding the overhead of mapping and unmapping it every frame, e.g. for
+    /// streaming uploads to [linear](BufferObjectFlags::LINEAR) buffers. Dropping the
+    /// returned [`PersistentMapGuard`] doesn't unmap the buffer object, it stays mapped
+    /// until [`PersistentMapGuard::unmap()`] is called or the buffer object is dropped.
+    ///
+    /// Whether writes through the mapping are visible to the gpu before it is unmapped
+    /// depends on the driver. Linear buffer objects are usually mapped directly and thus
+    /// coherent, while other buffer objects are mapped through a staging copy, that is
+    /// only written back on unmap. Call [`PersistentMapGuard::flush()`] after writing to
+    /// make sure the writes reach the buffer object.
+    pub fn persistent_map(&mut self) -> IoResult<PersistentMapGuard<'_, T>> {
+        self.check_cpu_access()?;
+        let mapping = match &mut self.persistent_mapping {
+            Some(mapping) => mapping,
+            None => {
+                let (width, height) = (self.width(), self.height());
+                let mapping = unsafe { PersistentMapping::new(*self.ffi, width, height)? };
+                self.persistent_mapping.insert(mapping)
+            }
+        };
+        let (ptr, stride) = (mapping.ptr, mapping.stride);
+        Ok(PersistentMapGuard {
+            bo: self,
+            ptr,
+            stride,
+            written: false,
+        })
+    }
+
     ///  Write data into the buffer object
     ///
     /// If the buffer object was created with the [`BufferObjectFlags::WRITE`] flag,
@@ -376,7 +1473,18 @@ impl<T: 'static> BufferObject<T> {
     /// data is copied directly into the object and it's the responsibility
     /// of the caller to make sure the data represents valid pixel data,
     /// according to the width, height, stride and format of the buffer object.
+    ///
+    /// Linear buffer objects are mapped and written to directly, which avoids an
+    /// additional copy in some drivers. If mapping fails, this falls back to `gbm_bo_write`.
+    ///
+    /// Fails with [`io::ErrorKind::PermissionDenied`] wrapping [`MapError::Protected`] for
+    /// buffer objects created with [`BufferObjectFlags::PROTECTED`].
     pub fn write(&mut self, buffer: &[u8]) -> IoResult<()> {
+        self.check_cpu_access()?;
+        self.mark_cpu_written();
+        if self.is_linear() && self.write_mapped(buffer) {
+            return Ok(());
+        }
         let result =
             unsafe { ffi::gbm_bo_write(*self.ffi, buffer.as_ptr() as *const _, buffer.len() as _) };
         if result != 0 {
@@ -386,10 +1494,198 @@ impl<T: 'static> BufferObject<T> {
         }
     }
 
+    /// Fill the buffer object with pixel data read from `reader`
+    ///
+    /// Maps the whole buffer object and reads its rows directly into the mapping, without
+    /// an intermediate buffer. The data is expected to be tightly packed, i.e. without
+    /// the stride padding, so exactly `height` rows of `width * bpp / 8` bytes are read.
+    /// Returns the number of bytes read.
+    ///
+    /// Fails with [`io::ErrorKind::UnexpectedEof`] if the reader ends early, in which case
+    /// the buffer object contains the rows read so far, and with
+    /// [`io::ErrorKind::InvalidInput`] for [planar](crate::planar_info()) formats.
+    pub fn write_from(&mut self, reader: &mut impl io::Read) -> IoResult<usize> {
+        let (width, height) = (self.width(), self.height());
+        let mut mapping = self.map_mut_guard(0, 0, width, height)?;
+        let (row_bytes, stride) = (mapping.row_bytes()?, mapping.stride() as usize);
+        let buffer = mapping.buffer_mut();
+        for row in 0..height as usize {
+            reader.read_exact(&mut buffer[row * stride..row * stride + row_bytes])?;
+        }
+        Ok(row_bytes * height as usize)
+    }
+
+    /// Read the pixel data of the buffer object into a vector
+    ///
+    /// Maps the whole buffer object and copies its rows, without the stride padding, so
+    /// the returned data is tightly packed in the layout expected by [`Self::write_from()`].
+    /// Fails with [`io::ErrorKind::InvalidInput`] for [planar](crate::planar_info()) formats.
+    pub fn read_to_vec(&self) -> IoResult<Vec<u8>> {
+        let mapping = self.map_guard(0, 0, self.width(), self.height())?;
+        let row_bytes = mapping.row_bytes()?;
+        let mut data = Vec::with_capacity(row_bytes * mapping.height() as usize);
+        for row in mapping.rows_exact(row_bytes) {
+            data.extend_from_slice(row);
+        }
+        Ok(data)
+    }
+
+    /// Compute a hash of the pixel data of the buffer object, e.g. to detect changes
+    ///
+    /// Maps the whole buffer object and hashes its rows with 64-bit FNV-1a, skipping the
+    /// stride padding, so buffers with equal contents but different strides hash equally.
+    /// The hash is not stable across versions of this crate and not suitable for anything
+    /// but change detection. Fails with [`io::ErrorKind::InvalidInput`] for
+    /// [planar](crate::planar_info()) formats.
+    ///
+    /// This reads every byte of the buffer, which is slow for large buffers and especially
+    /// for buffers in video memory or with a tiled layout, that the driver has to copy
+    /// or detile for the mapping.
+    pub fn content_hash(&self) -> IoResult<u64> {
+        let mapping = self.map_guard(0, 0, self.width(), self.height())?;
+        let (row_bytes, stride) = (mapping.row_bytes()?, mapping.stride() as usize);
+        let buffer = mapping.buffer();
+        let hash = (0..mapping.height() as usize).fold(FNV_OFFSET_BASIS, |hash, row| {
+            fnv1a(hash, &buffer[row * stride..row * stride + row_bytes])
+        });
+        Ok(hash)
+    }
+
+    // Protected buffers can't be accessed by the cpu, fail early instead of in the driver
+    fn check_cpu_access(&self) -> Result<(), MapError> {
+        match self.usage {
+            Some(usage) if usage.contains(BufferObjectFlags::PROTECTED) => Err(MapError::Protected),
+            _ => Ok(()),
+        }
+    }
+
+    fn is_linear(&self) -> bool {
+        self.usage
+            .map_or(false, |usage| usage.contains(BufferObjectFlags::LINEAR))
+            || self.modifier() == Modifier::Linear
+    }
+
+    // Copies `buffer` into a mapping of the whole buffer object, returns `false` if the
+    // buffer object couldn't be mapped or `buffer` doesn't fit
+    fn write_mapped(&mut self, buffer: &[u8]) -> bool {
+        let (width, height, stride) = (self.width(), self.height(), self.stride());
+        let size = stride as usize * height as usize;
+        if buffer.len() > size {
+            return false;
+        }
+        // Write-only mappings are only valid if every byte is written
+        let flags = if buffer.len() == size {
+            ffi::gbm_bo_transfer_flags::GBM_BO_TRANSFER_WRITE
+        } else {
+            ffi::gbm_bo_transfer_flags::GBM_BO_TRANSFER_READ_WRITE
+        };
+        let ffi = *self.ffi;
+        match unsafe { Self::map_region(ffi, BORef::Mut(self), 0, 0, width, height, flags) } {
+            Ok(mapping) if mapping.stride() == stride => {
+                mapping.buffer[..buffer.len()].copy_from_slice(buffer);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Write typed pixel data into the buffer object
+    ///
+    /// This is a thin wrapper around [`Self::write()`] casting the data to bytes.
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the data is larger than the
+    /// buffer object (`stride * height` bytes).
+    #[cfg(feature = "bytemuck")]
+    pub fn write_typed<P: bytemuck::Pod>(&mut self, data: &[P]) -> IoResult<()> {
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        let size = self.stride() as usize * self.height() as usize;
+        if bytes.len() > size {
+            return Err(IoError::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "data of {} bytes exceeds buffer size of {} bytes",
+                    bytes.len(),
+                    size
+                ),
+            ));
+        }
+        self.write(bytes)
+    }
+
+    /// Create another handle to the same buffer object
+    ///
+    /// The underlying `gbm_bo` is shared and only destroyed (or released back to its
+    /// [`Surface`](crate::Surface)) once all handles are dropped. Since the userdata of the
+    /// buffer object is stored on the `gbm_bo` and typed by the original handle, the new
+    /// handle is always a `BufferObject<()>`, that can't access the userdata: its userdata
+    /// accessors always return `None` and setting userdata through it has no effect.
+    pub fn dup(&self) -> BufferObject<()> {
+        let mut bo = BufferObject::from_ptr(
+            self.ffi.clone(),
+            self._device.clone(),
+            self.device_fd,
+            self.usage,
+        );
+        bo.plane_count = AtomicU32::new(self.plane_count.load(Ordering::Relaxed));
+        bo.requested_modifiers = self.requested_modifiers.clone();
+        bo.foreign_userdata = true;
+        bo
+    }
+
+    /// Destroy the buffer object now, reporting errors of the driver
+    ///
+    /// Dropping a buffer object destroys it without a way to report failures. This
+    /// destroys it with `gbm_bo_destroy`, or releases it to its surface for buffer objects
+    /// locked from a [`Surface`](crate::Surface). Any userdata, except for userdata of
+    /// buffer objects locked from a surface, is dropped and a
+    /// [persistent mapping](Self::persistent_map()) is unmapped before.
+    ///
+    /// Neither function returns an error, so this is best-effort: `errno` is cleared
+    /// right before the call and reported, if libgbm set it. Drivers aren't required to
+    /// set it on failure and might leave it set by internal calls on success, so an error
+    /// doesn't guarantee the buffer object wasn't destroyed.
+    ///
+    /// The underlying buffer object is shared by the handles returned by [`Self::dup()`].
+    /// If any of them is still alive, the buffer object can't be destroyed and an error
+    /// of kind [`io::ErrorKind::Other`] is returned instead. This handle is dropped in
+    /// either case.
+    pub fn destroy(mut self) -> IoResult<()> {
+        let shared = self.ffi.ref_count() - 1;
+        if shared > 0 {
+            return Err(IoError::new(
+                io::ErrorKind::Other,
+                format!(
+                    "buffer object is still referenced by {} other handles",
+                    shared
+                ),
+            ));
+        }
+        // Drop the userdata and unmap first, so `errno` only reflects the destruction
+        self.persistent_mapping = None;
+        if !self.surface_owned {
+            drop(self.take_userdata());
+        }
+        let BufferObject { ffi, .. } = self;
+        crate::surface::clear_errno();
+        if ffi.try_drop().is_err() {
+            return Err(IoError::new(
+                io::ErrorKind::Other,
+                "buffer object was referenced concurrently",
+            ));
+        }
+        match IoError::last_os_error() {
+            err if err.raw_os_error() == Some(0) => Ok(()),
+            err => Err(err),
+        }
+    }
+
     /// Sets the userdata of the buffer object.
     ///
     /// If previously userdata was set, it is returned.
+    /// For handles created by [`Self::dup()`] the userdata isn't set and is returned instead.
     pub fn set_userdata(&mut self, userdata: T) -> Option<T> {
+        if self.foreign_userdata {
+            return Some(userdata);
+        }
         let old = self.take_userdata();
 
         let boxed = Box::new(userdata);
@@ -411,6 +1707,9 @@ impl<T: 'static> BufferObject<T> {
 
     /// Returns a reference to set userdata, if any.
     pub fn userdata(&self) -> Option<&T> {
+        if self.foreign_userdata {
+            return None;
+        }
         let raw = unsafe { ffi::gbm_bo_get_user_data(*self.ffi) };
 
         if raw.is_null() {
@@ -422,6 +1721,9 @@ impl<T: 'static> BufferObject<T> {
 
     /// Returns a mutable reference to set userdata, if any.
     pub fn userdata_mut(&mut self) -> Option<&mut T> {
+        if self.foreign_userdata {
+            return None;
+        }
         let raw = unsafe { ffi::gbm_bo_get_user_data(*self.ffi) };
 
         if raw.is_null() {
@@ -435,6 +1737,9 @@ impl<T: 'static> BufferObject<T> {
     ///
     /// This removes the userdata from the buffer object.
     pub fn take_userdata(&mut self) -> Option<T> {
+        if self.foreign_userdata {
+            return None;
+        }
         let raw = unsafe { ffi::gbm_bo_get_user_data(*self.ffi) };
 
         if raw.is_null() {
@@ -451,34 +1756,125 @@ impl<T: 'static> BufferObject<T> {
     pub(crate) unsafe fn new(
         ffi: *mut ffi::gbm_bo,
         device: Ptr<ffi::gbm_device>,
+        device_fd: RawFd,
+        usage: Option<BufferObjectFlags>,
+    ) -> BufferObject<T> {
+        BufferObject::from_ptr(
+            Ptr::<ffi::gbm_bo>::new(ffi, |ptr| ffi::gbm_bo_destroy(ptr)),
+            device,
+            device_fd,
+            usage,
+        )
+    }
+
+    pub(crate) fn from_ptr(
+        ffi: Ptr<ffi::gbm_bo>,
+        device: Ptr<ffi::gbm_device>,
+        device_fd: RawFd,
+        usage: Option<BufferObjectFlags>,
     ) -> BufferObject<T> {
         BufferObject {
-            ffi: Ptr::<ffi::gbm_bo>::new(ffi, |ptr| ffi::gbm_bo_destroy(ptr)),
+            persistent_mapping: None,
+            ffi,
             _device: device,
+            device_fd,
             _userdata: PhantomData,
+            usage,
+            plane_count: AtomicU32::new(UNKNOWN_PLANE_COUNT),
+            requested_modifiers: None,
+            cached_fd: Mutex::new(None),
+            foreign_userdata: false,
+            surface_owned: false,
+            #[cfg(debug_assertions)]
+            cpu_dirty: AtomicBool::new(false),
+            #[cfg(debug_assertions)]
+            read_maps: Mutex::new(Vec::new()),
+        }
+    }
+
+    // Debug builds track cpu writes to warn about reads without synchronization in between,
+    // which might return stale data on some drivers
+    fn mark_cpu_written(&self) {
+        #[cfg(debug_assertions)]
+        self.cpu_dirty.store(true, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "dma-buf-sync")]
+    pub(crate) fn mark_synchronized(&self) {
+        #[cfg(debug_assertions)]
+        self.cpu_dirty.store(false, Ordering::Relaxed);
+    }
+
+    fn check_read_hazard(&self) {
+        #[cfg(debug_assertions)]
+        if self.cpu_dirty.swap(false, Ordering::Relaxed) {
+            log::warn!(
+                "Buffer object {:p} is mapped for reading after a cpu write without synchronization, \
+                 the read might return stale data",
+                *self.ffi
+            );
+        }
+    }
+
+    // Debug builds track the regions mapped for reading at the same time, as some drivers
+    // mishandle overlapping maps, e.g. by unmapping staging copies in the wrong order
+    #[cfg(debug_assertions)]
+    fn track_read_map(&self, region: (u32, u32, u32, u32)) {
+        let overlaps = |(x, y, width, height): (u32, u32, u32, u32)| {
+            x < region.0 + region.2
+                && region.0 < x + width
+                && y < region.1 + region.3
+                && region.1 < y + height
+        };
+        let mut read_maps = self.read_maps.lock().unwrap();
+        if let Some(other) = read_maps.iter().find(|other| overlaps(**other)) {
+            log::warn!(
+                "Buffer object {:p} is mapped at {:?} while the overlapping region {:?} is still mapped, \
+                 some drivers corrupt the data of overlapping maps",
+                *self.ffi,
+                region,
+                other
+            );
+        }
+        read_maps.push(region);
+    }
+
+    #[cfg(debug_assertions)]
+    fn untrack_read_map(&self, region: (u32, u32, u32, u32)) {
+        let mut read_maps = self.read_maps.lock().unwrap();
+        if let Some(index) = read_maps.iter().position(|other| *other == region) {
+            read_maps.swap_remove(index);
+        }
+    }
+
+    pub(crate) fn with_requested_modifiers(mut self, modifiers: Vec<Modifier>) -> Self {
+        self.requested_modifiers = Some(modifiers.into_boxed_slice());
+        #[cfg(debug_assertions)]
+        if self.modifiers_ignored() {
+            log::warn!(
+                "Driver ignored the requested modifiers {:?} of buffer object {:p} and chose an implicit layout",
+                self.requested_modifiers.as_deref().unwrap_or_default(),
+                *self.ffi
+            );
         }
+        self
     }
 
     fn offsets(&self) -> [u32; 4] {
-        let num = self.plane_count();
-        [
-            BufferObject::<T>::offset(self, 0),
-            if num > 1 {
-                BufferObject::<T>::offset(self, 1)
-            } else {
-                0
-            },
-            if num > 2 {
-                BufferObject::<T>::offset(self, 2)
-            } else {
-                0
-            },
-            if num > 3 {
-                BufferObject::<T>::offset(self, 3)
-            } else {
-                0
-            },
-        ]
+        let mut offsets = [0; 4];
+        for (offset, plane) in offsets.iter_mut().zip(self.planes()) {
+            *offset = plane.offset;
+        }
+        offsets
+    }
+}
+
+impl<T: 'static> TryFrom<BufferObject<T>> for OwnedFd {
+    type Error = IoError;
+
+    /// See [`BufferObject::into_fd()`]
+    fn try_from(bo: BufferObject<T>) -> IoResult<OwnedFd> {
+        bo.into_fd()
     }
 }
 
@@ -520,63 +1916,19 @@ impl<T: 'static> DrmPlanarBuffer for BufferObject<T> {
         Some(BufferObject::<T>::modifier(self))
     }
     fn pitches(&self) -> [u32; 4] {
-        let num = self.plane_count();
-        [
-            BufferObject::<T>::stride_for_plane(self, 0),
-            if num > 1 {
-                BufferObject::<T>::stride_for_plane(self, 1)
-            } else {
-                0
-            },
-            if num > 2 {
-                BufferObject::<T>::stride_for_plane(self, 2)
-            } else {
-                0
-            },
-            if num > 3 {
-                BufferObject::<T>::stride_for_plane(self, 3)
-            } else {
-                0
-            },
-        ]
+        let mut pitches = [0; 4];
+        for (pitch, plane) in pitches.iter_mut().zip(self.planes()) {
+            *pitch = plane.stride;
+        }
+        pitches
     }
     fn handles(&self) -> [Option<Handle>; 4] {
         use std::num::NonZeroU32;
-        let num = self.plane_count();
-        [
-            Some(unsafe {
-                Handle::from(NonZeroU32::new_unchecked(
-                    BufferObject::<T>::handle_for_plane(self, 0).u32_,
-                ))
-            }),
-            if num > 1 {
-                Some(unsafe {
-                    Handle::from(NonZeroU32::new_unchecked(
-                        BufferObject::<T>::handle_for_plane(self, 1).u32_,
-                    ))
-                })
-            } else {
-                None
-            },
-            if num > 2 {
-                Some(unsafe {
-                    Handle::from(NonZeroU32::new_unchecked(
-                        BufferObject::<T>::handle_for_plane(self, 2).u32_,
-                    ))
-                })
-            } else {
-                None
-            },
-            if num > 3 {
-                Some(unsafe {
-                    Handle::from(NonZeroU32::new_unchecked(
-                        BufferObject::<T>::handle_for_plane(self, 3).u32_,
-                    ))
-                })
-            } else {
-                None
-            },
-        ]
+        let mut handles = [None; 4];
+        for (handle, plane) in handles.iter_mut().zip(self.planes()) {
+            *handle = NonZeroU32::new(unsafe { plane.handle.u32_ }).map(Handle::from);
+        }
+        handles
     }
     fn offsets(&self) -> [u32; 4] {
         self.offsets()
@@ -594,3 +1946,190 @@ impl fmt::Display for InvalidFdError {
 }
 
 impl error::Error for InvalidFdError {}
+
+impl From<InvalidFdError> for IoError {
+    fn from(err: InvalidFdError) -> Self {
+        IoError::new(io::ErrorKind::Other, err)
+    }
+}
+
+/// Errors that may happen when mapping a buffer object
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MapError {
+    /// The buffer object is busy, mapping it may succeed when retried later
+    Busy,
+    /// The buffer object can't be mapped, e.g. because of its layout or because the
+    /// driver doesn't support mapping it
+    ///
+    /// Fall back to other ways of accessing the buffer, e.g. [`BufferObject::write()`]
+    /// or copying it into a [linear](BufferObjectFlags::LINEAR) buffer on the gpu.
+    NotMappable,
+    /// The requested region exceeds the dimensions of the buffer object
+    InvalidRegion,
+    /// The buffer object was created with [`BufferObjectFlags::PROTECTED`]
+    ///
+    /// Protected buffers can't be accessed by the cpu, neither through mapping nor
+    /// through [`BufferObject::write()`].
+    Protected,
+    /// Any other error reported by libgbm
+    Other(IoError),
+}
+
+impl MapError {
+    fn from_os_error(err: IoError) -> MapError {
+        match err.raw_os_error() {
+            Some(libc::EBUSY) | Some(libc::EAGAIN) => MapError::Busy,
+            Some(libc::EINVAL) | Some(libc::ENOSYS) | Some(libc::EOPNOTSUPP) => {
+                MapError::NotMappable
+            }
+            _ => MapError::Other(err),
+        }
+    }
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::Busy => write!(f, "Buffer object is busy"),
+            MapError::NotMappable => write!(f, "Buffer object can't be mapped"),
+            MapError::InvalidRegion => write!(f, "Region exceeds the buffer object"),
+            MapError::Protected => {
+                write!(f, "Protected buffer object can't be accessed by the cpu")
+            }
+            MapError::Other(err) => write!(f, "Failed to map buffer object: {}", err),
+        }
+    }
+}
+
+impl error::Error for MapError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            MapError::Other(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<MapError> for IoError {
+    fn from(err: MapError) -> Self {
+        let kind = match err {
+            MapError::Busy => io::ErrorKind::WouldBlock,
+            MapError::NotMappable => io::ErrorKind::Unsupported,
+            MapError::InvalidRegion => io::ErrorKind::InvalidInput,
+            MapError::Protected => io::ErrorKind::PermissionDenied,
+            MapError::Other(err) => return err,
+        };
+        IoError::new(kind, err)
+    }
+}
+
+// Size of a tile of the layout described by `modifier` in pixels, or 1x1 for unknown layouts
+fn tile_size(modifier: Modifier, bpp: u32) -> (u32, u32) {
+    // Tiles of a fixed width in bytes
+    let bytes = |width: u32, height: u32| match width.checked_mul(8).map(|bits| bits / bpp) {
+        Some(pixels) if bpp > 0 && pixels > 0 => (pixels, height),
+        _ => (1, 1),
+    };
+    match modifier {
+        Modifier::I915_x_tiled => bytes(512, 8),
+        Modifier::I915_y_tiled
+        | Modifier::I915_y_tiled_ccs
+        | Modifier::I915_y_tiled_gen12_rc_ccs
+        | Modifier::I915_y_tiled_gen12_mc_ccs => bytes(128, 32),
+        Modifier::Nvidia_tegra_tiled => bytes(16, 16),
+        Modifier::Samsung_64_32_tile => (64, 32),
+        Modifier::Samsung_16_16_tile | Modifier::Generic_16_16_tile => (16, 16),
+        Modifier::Vivante_tiled => (4, 4),
+        Modifier::Vivante_super_tiled => (64, 64),
+        Modifier::Allwinner_tiled => (32, 32),
+        _ => (1, 1),
+    }
+}
+
+// Expands `region` outwards to multiples of `tile`, clamped to `size`. Returns `None` if
+// the region exceeds `size`.
+fn align_to_tiles(
+    (x, y, width, height): (u32, u32, u32, u32),
+    (bo_width, bo_height): (u32, u32),
+    (tile_width, tile_height): (u32, u32),
+) -> Option<(u32, u32, u32, u32)> {
+    let align = |start: u32, len: u32, size: u32, tile: u32| {
+        let end = start.checked_add(len).filter(|end| *end <= size)?;
+        let start = start - start % tile;
+        let end = end
+            .checked_add((tile - end % tile) % tile)
+            .map_or(size, |end| end.min(size));
+        Some((start, end - start))
+    };
+    let (x, width) = align(x, width, bo_width, tile_width)?;
+    let (y, height) = align(y, height, bo_height, tile_height)?;
+    Some((x, y, width, height))
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{align_to_tiles, fnv1a, tile_size, MapError, FNV_OFFSET_BASIS};
+    use crate::Modifier;
+    use std::io;
+
+    #[test]
+    fn map_error_classification() {
+        let classify = |errno| MapError::from_os_error(io::Error::from_raw_os_error(errno));
+        assert!(matches!(classify(libc::EBUSY), MapError::Busy));
+        assert!(matches!(classify(libc::ENOSYS), MapError::NotMappable));
+        assert!(matches!(classify(libc::ENOMEM), MapError::Other(_)));
+    }
+
+    #[test]
+    fn map_error_into_io_error() {
+        assert_eq!(
+            io::Error::from(MapError::Busy).kind(),
+            io::ErrorKind::WouldBlock
+        );
+        assert_eq!(
+            io::Error::from(MapError::Protected).kind(),
+            io::ErrorKind::PermissionDenied
+        );
+        let err = io::Error::from(MapError::Other(io::Error::from_raw_os_error(libc::ENOMEM)));
+        assert_eq!(err.raw_os_error(), Some(libc::ENOMEM));
+    }
+
+    #[test]
+    fn fnv1a_is_incremental() {
+        assert_eq!(fnv1a(FNV_OFFSET_BASIS, b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(
+            fnv1a(fnv1a(FNV_OFFSET_BASIS, b"foo"), b"bar"),
+            fnv1a(FNV_OFFSET_BASIS, b"foobar")
+        );
+    }
+
+    #[test]
+    fn regions_expand_to_tiles() {
+        let tile = tile_size(Modifier::I915_x_tiled, 32);
+        assert_eq!(tile, (128, 8));
+        assert_eq!(
+            align_to_tiles((130, 3, 10, 2), (1000, 100), tile),
+            Some((128, 0, 128, 8))
+        );
+        // Partial tiles at the edges are clamped to the buffer
+        assert_eq!(
+            align_to_tiles((900, 90, 100, 10), (1000, 100), tile),
+            Some((896, 88, 104, 12))
+        );
+        assert_eq!(align_to_tiles((900, 0, 101, 1), (1000, 100), tile), None);
+        assert_eq!(
+            align_to_tiles((5, 7, 3, 2), (1000, 100), tile_size(Modifier::Linear, 32)),
+            Some((5, 7, 3, 2))
+        );
+    }
+}
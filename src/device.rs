@@ -1,11 +1,21 @@
+use crate::symbols;
 use crate::{AsRaw, BufferObject, BufferObjectFlags, Format, Modifier, Ptr, Surface};
 
-use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd};
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+#[cfg(feature = "import-egl")]
+use std::ptr::NonNull;
 
+use std::collections::HashMap;
+use std::env;
+use std::error;
 use std::ffi::CStr;
 use std::fmt;
-use std::io::{Error as IoError, Result as IoResult};
+use std::fs;
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
 use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 #[cfg(feature = "import-wayland")]
 use wayland_server::protocol::wl_buffer::WlBuffer;
@@ -14,18 +24,313 @@ use wayland_server::protocol::wl_buffer::WlBuffer;
 /// An EGLImage handle
 pub type EGLImage = *mut libc::c_void;
 
+/// A non-null [`EGLImage`] asserted to be valid for importing via [`ImportSource::EglImage`]
+#[cfg(feature = "import-egl")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EglImageSource(NonNull<libc::c_void>);
+
+#[cfg(feature = "import-egl")]
+impl EglImageSource {
+    /// Wrap an [`EGLImage`] for importing, returning `None` if it is null
+    ///
+    /// # Safety
+    ///
+    /// The given [`EGLImage`] must be a valid `EGLImage` for as long as the returned
+    /// value is used for imports.
+    pub unsafe fn new(image: EGLImage) -> Option<EglImageSource> {
+        NonNull::new(image).map(EglImageSource)
+    }
+
+    /// The raw [`EGLImage`] handle
+    pub fn as_ptr(&self) -> EGLImage {
+        self.0.as_ptr()
+    }
+}
+
+/// A foreign buffer to import as a [`BufferObject`] with [`Device::import()`]
+#[derive(Debug, Clone, Copy)]
+pub enum ImportSource<'a> {
+    /// A wayland buffer
+    #[cfg(feature = "import-wayland")]
+    WlBuffer(&'a WlBuffer),
+    /// An EGLImage
+    #[cfg(feature = "import-egl")]
+    EglImage(EglImageSource),
+    /// A single plane dma buffer
+    Dmabuf {
+        /// File descriptor of the dma buffer
+        fd: BorrowedFd<'a>,
+        /// Width of the buffer
+        width: u32,
+        /// Height of the buffer
+        height: u32,
+        /// Stride of the buffer
+        stride: u32,
+        /// Format of the buffer
+        format: Format,
+    },
+    /// A dma buffer with explicit modifier and up to four planes
+    DmabufModifier {
+        /// Number of planes
+        len: u32,
+        /// File descriptors of the planes
+        fds: [Option<BorrowedFd<'a>>; 4],
+        /// Width of the buffer
+        width: u32,
+        /// Height of the buffer
+        height: u32,
+        /// Format of the buffer
+        format: Format,
+        /// Strides of the planes
+        strides: [i32; 4],
+        /// Offsets of the planes
+        offsets: [i32; 4],
+        /// Modifier of the buffer
+        modifier: Modifier,
+    },
+}
+
 #[cfg(feature = "drm-support")]
 use drm::control::Device as DrmControlDevice;
 #[cfg(feature = "drm-support")]
 use drm::Device as DrmDevice;
 
+type AllocObserver = Arc<dyn Fn(&AllocEvent<'_>) + Send + Sync>;
+
+type OomHandler = Arc<dyn Fn() -> bool + Send + Sync>;
+
+type ModifierSupportCache = HashMap<(Format, Modifier, BufferObjectFlags), bool>;
+
+// `None` until the framebuffer limits were queried
+type MaxDimensionsCache = Option<Option<(u32, u32)>>;
+
+/// Builder for importing a multi-planar dma buffer
+///
+/// This assembles an [`ImportSource::DmabufModifier`] for [`Device::import()`] from
+/// planes added in order, deriving the number of planes from the added ones.
+///
+/// ```rust,no_run
+/// # use std::os::unix::io::BorrowedFd;
+/// # use gbm::{BufferObjectFlags, Device, DmabufImportBuilder, Format, Modifier};
+/// # fn import(gbm: &Device<std::fs::File>, y: BorrowedFd<'_>, uv: BorrowedFd<'_>) -> std::io::Result<()> {
+/// let source = DmabufImportBuilder::new()
+///     .dimensions(1920, 1080)
+///     .format(Format::Nv12)
+///     .modifier(Modifier::Linear)
+///     .plane(y, 0, 1920)
+///     .plane(uv, 0, 1920)
+///     .build()?;
+/// let bo = gbm.import::<()>(source, BufferObjectFlags::SCANOUT)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DmabufImportBuilder<'a> {
+    planes: Vec<(BorrowedFd<'a>, u32, u32)>,
+    dimensions: Option<(u32, u32)>,
+    format: Option<Format>,
+    modifier: Option<Modifier>,
+}
+
+impl<'a> DmabufImportBuilder<'a> {
+    /// Create a new empty builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the next plane with its file descriptor, offset and stride
+    pub fn plane(mut self, fd: BorrowedFd<'a>, offset: u32, stride: u32) -> Self {
+        self.planes.push((fd, offset, stride));
+        self
+    }
+
+    /// Set the width and height of the buffer
+    pub fn dimensions(mut self, width: u32, height: u32) -> Self {
+        self.dimensions = Some((width, height));
+        self
+    }
+
+    /// Set the format of the buffer
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Set the modifier of the buffer
+    ///
+    /// Defaults to `Modifier::Invalid`, i.e. an implicit layout.
+    pub fn modifier(mut self, modifier: Modifier) -> Self {
+        self.modifier = Some(modifier);
+        self
+    }
+
+    /// Validate the parameters and build the import source
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] if the dimensions or format are missing,
+    /// no or more than four planes were added or an offset or stride is out of range.
+    pub fn build(self) -> IoResult<ImportSource<'a>> {
+        fn invalid(msg: &str) -> IoError {
+            IoError::new(ErrorKind::InvalidInput, msg)
+        }
+
+        let (width, height) = self
+            .dimensions
+            .ok_or_else(|| invalid("dimensions of the dma buffer are not set"))?;
+        let format = self
+            .format
+            .ok_or_else(|| invalid("format of the dma buffer is not set"))?;
+        if self.planes.is_empty() || self.planes.len() > 4 {
+            return Err(invalid(
+                "dma buffers need to have between one and four planes",
+            ));
+        }
+
+        let mut fds = [None; 4];
+        let mut offsets = [0; 4];
+        let mut strides = [0; 4];
+        for (i, (fd, offset, stride)) in self.planes.iter().enumerate() {
+            fds[i] = Some(*fd);
+            offsets[i] = i32::try_from(*offset).map_err(|_| invalid("plane offset too large"))?;
+            strides[i] = i32::try_from(*stride).map_err(|_| invalid("plane stride too large"))?;
+        }
+
+        Ok(ImportSource::DmabufModifier {
+            len: self.planes.len() as u32,
+            fds,
+            width,
+            height,
+            format,
+            strides,
+            offsets,
+            modifier: self.modifier.unwrap_or(Modifier::Invalid),
+        })
+    }
+}
+
 /// An open GBM device
+///
+/// # Thread safety
+///
+/// A `Device` is `Send` and `Sync` if its file descriptor type `T` is, so it can be
+/// moved or shared into a blocking task to allocate buffer objects off an async
+/// executor, e.g. with `tokio::task::spawn_blocking`. Use [`Self::into_shared()`] to get
+/// a cheaply cloneable device to hand to such tasks:
+///
+/// ```rust,no_run
+/// # fn offload(device: gbm::SharedDevice<std::fs::File>) {
+/// let task_device = device.clone();
+/// let handle = std::thread::spawn(move || {
+///     task_device.create_buffer_object::<()>(
+///         1920,
+///         1080,
+///         gbm::Format::Argb8888,
+///         gbm::BufferObjectFlags::RENDERING,
+///     )
+/// });
+/// let bo = handle.join().unwrap();
+/// # }
+/// ```
+///
+/// libgbm doesn't document guarantees about concurrent use of a device, but Mesa's
+/// implementation guards its shared state internally. Drivers may serialize calls, so
+/// allocations from multiple threads don't necessarily run in parallel. Userdata `U` of the
+/// created buffer objects must be `Send` to return them from the task, and an
+/// [allocation observer](Self::set_alloc_observer()) is called on the allocating thread.
+///
+/// Backends other than Mesa's might require all calls to happen on the thread that
+/// created the device, use [`LocalDevice`](crate::LocalDevice) to confine a device to
+/// its thread.
+#[must_use = "dropping this immediately destroys the GBM device"]
 pub struct Device<T: AsFd> {
     // Declare `ffi` first so it is dropped before `fd`
-    ffi: Ptr<ffi::gbm_device>,
+    pub(crate) ffi: Ptr<ffi::gbm_device>,
+    fd: T,
+    // `gbm_device_get_fd` is stable for the lifetime of the device, so it is only queried once
+    pub(crate) raw_fd: RawFd,
+    // Framebuffer limits of the DRM device, queried on the first scanout allocation
+    max_dimensions: Arc<Mutex<MaxDimensionsCache>>,
+    modifier_support: Arc<Mutex<ModifierSupportCache>>,
+    alloc_observer: Option<AllocObserver>,
+    oom_handler: Option<OomHandler>,
+    force_linear: bool,
+}
+
+/// Parameters and duration of a buffer object allocation
+///
+/// Passed to the observer set with [`Device::set_alloc_observer()`].
+#[derive(Debug, Clone, Copy)]
+pub struct AllocEvent<'a> {
+    /// The requested width
+    pub width: u32,
+    /// The requested height
+    pub height: u32,
+    /// The requested format
+    pub format: Format,
+    /// The requested modifiers, if allocated with an explicit list of modifiers
+    pub modifiers: Option<&'a [Modifier]>,
+    /// The requested usage flags, if allocated with explicit flags
+    pub usage: Option<BufferObjectFlags>,
+    /// Time spent in libgbm allocating the buffer object
+    pub elapsed: Duration,
+    /// Whether the allocation succeeded
+    pub success: bool,
+}
+
+/// Builder for a [`Device`] with additional options
+///
+/// [`Device::new()`] is equivalent to `DeviceBuilder::new(fd).build()`.
+#[derive(Debug)]
+pub struct DeviceBuilder<T: AsFd> {
     fd: T,
+    backend: Option<String>,
+    force_linear: bool,
+}
+
+impl<T: AsFd> DeviceBuilder<T> {
+    /// Create a builder for a device using the given open DRM device
+    pub fn new(fd: T) -> DeviceBuilder<T> {
+        DeviceBuilder {
+            fd,
+            backend: None,
+            force_linear: false,
+        }
+    }
+
+    /// Force a specific GBM backend, see [`Device::new_with_backend()`]
+    pub fn backend(mut self, backend: impl Into<String>) -> Self {
+        self.backend = Some(backend.into());
+        self
+    }
+
+    /// Add [`BufferObjectFlags::LINEAR`] to the usage flags of all buffer objects and
+    /// surfaces created with [`Device::create_buffer_object()`] and [`Device::create_surface()`]
+    ///
+    /// Allocations with explicit modifiers are not affected.
+    pub fn force_linear(mut self, force_linear: bool) -> Self {
+        self.force_linear = force_linear;
+        self
+    }
+
+    /// Open the GBM device
+    pub fn build(self) -> IoResult<Device<T>> {
+        let mut device = match &self.backend {
+            Some(backend) => Device::new_with_backend(self.fd, backend)?,
+            None => Device::new(self.fd)?,
+        };
+        device.force_linear = self.force_linear;
+        Ok(device)
+    }
 }
 
+/// A GBM device sharing its file descriptor through an [`Arc`]
+///
+/// Unlike [`Device<T>`] this is clonable regardless of `T`, e.g. for an [`OwnedFd`],
+/// and can be used to create buffer objects and surfaces from multiple threads.
+/// Created by [`Device::into_shared()`].
+///
+/// [`OwnedFd`]: std::os::unix::io::OwnedFd
+pub type SharedDevice<T> = Device<Arc<T>>;
+
 impl<T: AsFd> fmt::Debug for Device<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Device")
@@ -39,13 +344,19 @@ impl<T: AsFd + Clone> Clone for Device<T> {
         Device {
             fd: self.fd.clone(),
             ffi: self.ffi.clone(),
+            raw_fd: self.raw_fd,
+            max_dimensions: self.max_dimensions.clone(),
+            modifier_support: self.modifier_support.clone(),
+            alloc_observer: self.alloc_observer.clone(),
+            oom_handler: self.oom_handler.clone(),
+            force_linear: self.force_linear,
         }
     }
 }
 
 impl<T: AsFd> AsFd for Device<T> {
-    fn as_fd(&self) -> BorrowedFd {
-        unsafe { BorrowedFd::borrow_raw(ffi::gbm_device_get_fd(*self.ffi)) }
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.raw_fd) }
     }
 }
 
@@ -68,6 +379,23 @@ impl<T: AsFd> DerefMut for Device<T> {
     }
 }
 
+impl Device<BorrowedFd<'static>> {
+    /// Open a GBM device from a raw file descriptor of an open DRM device
+    ///
+    /// This is meant for file descriptors owned by types only implementing [`AsRawFd`].
+    /// The returned device doesn't own the file descriptor and won't close it.
+    ///
+    /// # Safety
+    /// `fd` must be a valid open file descriptor, that stays open until the device and
+    /// all surfaces and buffer objects created from it are dropped.
+    pub unsafe fn new_from_raw_fd(fd: RawFd) -> IoResult<Device<BorrowedFd<'static>>> {
+        if fd < 0 {
+            return Err(IoError::from_raw_os_error(libc::EBADF));
+        }
+        Device::new(BorrowedFd::borrow_raw(fd))
+    }
+}
+
 impl<T: AsFd> Device<T> {
     /// Open a GBM device from a given open DRM device.
     ///
@@ -79,15 +407,103 @@ impl<T: AsFd> Device<T> {
         if ptr.is_null() {
             Err(IoError::last_os_error())
         } else {
+            let raw_fd = unsafe { ffi::gbm_device_get_fd(ptr) };
+            debug_assert_eq!(raw_fd, fd.as_fd().as_raw_fd());
             Ok(Device {
                 fd,
                 ffi: Ptr::<ffi::gbm_device>::new(ptr, |ptr| unsafe {
                     ffi::gbm_device_destroy(ptr)
                 }),
+                raw_fd,
+                max_dimensions: Arc::new(Mutex::new(None)),
+                modifier_support: Arc::new(Mutex::new(HashMap::new())),
+                alloc_observer: None,
+                oom_handler: None,
+                force_linear: false,
             })
         }
     }
 
+    /// Open a GBM device and check that it can allocate buffer objects
+    ///
+    /// On misconfigured systems, e.g. with a mismatch between the kernel driver and the
+    /// userspace driver, creating a device can succeed while allocations fail or crash
+    /// inside the driver. Like [`Self::new()`], but additionally allocates and frees a
+    /// 1x1 [`Format::Argb8888`] buffer object for rendering, returning an error of the
+    /// same kind if that fails. A driver crashing on allocation still crashes the
+    /// process, but when opening the device rather than on the first frame.
+    ///
+    /// The allocation makes opening the device noticeably slower on some drivers, as it
+    /// might initialize state otherwise set up lazily.
+    pub fn new_validated(fd: T) -> IoResult<Device<T>> {
+        let device = Device::new(fd)?;
+        if let Err(err) =
+            device.create_buffer_object::<()>(1, 1, Format::Argb8888, BufferObjectFlags::RENDERING)
+        {
+            return Err(IoError::new(
+                err.kind(),
+                format!("GBM device failed to allocate a test buffer: {}", err),
+            ));
+        }
+        Ok(device)
+    }
+
+    /// Convert into a [`SharedDevice`], which can be cloned without `T: Clone`
+    pub fn into_shared(self) -> SharedDevice<T> {
+        Device {
+            ffi: self.ffi,
+            fd: Arc::new(self.fd),
+            raw_fd: self.raw_fd,
+            max_dimensions: self.max_dimensions,
+            modifier_support: self.modifier_support,
+            alloc_observer: self.alloc_observer,
+            oom_handler: self.oom_handler,
+            force_linear: self.force_linear,
+        }
+    }
+
+    /// Open a GBM device from a given open DRM device, forcing a specific backend
+    ///
+    /// libgbm selects the backend through the `GBM_BACKEND` environment variable. This sets
+    /// the variable to `backend` while creating the device and restores the previous value
+    /// afterwards. The builtin mesa backend is selected with `"dri"`, other values load
+    /// the corresponding external backend, e.g. `"nvidia-drm"`.
+    ///
+    /// Since libgbm silently falls back to other backends, an error of kind
+    /// [`ErrorKind::NotFound`] is returned if the created device doesn't use the requested
+    /// backend.
+    ///
+    /// # Caveats
+    ///
+    /// Modifying the environment is racy, if other threads read or write environment
+    /// variables at the same time. This is mostly intended for tests and tools, that want
+    /// to deterministically select e.g. a software backend.
+    pub fn new_with_backend(fd: T, backend: &str) -> IoResult<Device<T>> {
+        const GBM_BACKEND: &str = "GBM_BACKEND";
+
+        let previous = env::var_os(GBM_BACKEND);
+        env::set_var(GBM_BACKEND, backend);
+        let device = Device::new(fd);
+        match previous {
+            Some(value) => env::set_var(GBM_BACKEND, value),
+            None => env::remove_var(GBM_BACKEND),
+        }
+
+        let device = device?;
+        let name = device.backend_name();
+        // The builtin backend is selected as "dri", but reports itself as "drm"
+        if name != backend && !(backend == "dri" && name == "drm") {
+            return Err(IoError::new(
+                ErrorKind::NotFound,
+                format!(
+                    "GBM backend \"{}\" could not be loaded, got \"{}\"",
+                    backend, name
+                ),
+            ));
+        }
+        Ok(device)
+    }
+
     /// Get the backend name
     pub fn backend_name(&self) -> &str {
         unsafe {
@@ -97,11 +513,190 @@ impl<T: AsFd> Device<T> {
         }
     }
 
+    /// Resolve the path of the device node this device was opened from
+    ///
+    /// This is a best-effort operation, first trying to read the `/proc/self/fd` link of the
+    /// device file descriptor and then falling back to looking up the device number in sysfs.
+    /// An error is returned if neither succeeds, e.g. when `/proc` and `/sys` aren't mounted.
+    pub fn device_path(&self) -> IoResult<PathBuf> {
+        if let Ok(path) = fs::read_link(format!("/proc/self/fd/{}", self.raw_fd)) {
+            if path.is_absolute() && path.exists() {
+                return Ok(path);
+            }
+        }
+
+        let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+        if unsafe { libc::fstat(self.raw_fd, stat.as_mut_ptr()) } != 0 {
+            return Err(IoError::last_os_error());
+        }
+        let rdev = unsafe { stat.assume_init() }.st_rdev;
+        let uevent = fs::read_to_string(format!(
+            "/sys/dev/char/{}:{}/uevent",
+            libc::major(rdev),
+            libc::minor(rdev)
+        ))?;
+        uevent
+            .lines()
+            .find_map(|line| line.strip_prefix("DEVNAME="))
+            .map(|name| Path::new("/dev").join(name))
+            .ok_or_else(|| IoError::new(ErrorKind::NotFound, "device has no device node"))
+    }
+
+    /// Get the type of the DRM node the device was opened on
+    ///
+    /// Returns `None` if the file descriptor doesn't refer to a DRM device node.
+    pub fn node_type(&self) -> Option<NodeType> {
+        const DRM_MAJOR: u32 = 226;
+
+        let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+        if unsafe { libc::fstat(self.raw_fd, stat.as_mut_ptr()) } != 0 {
+            return None;
+        }
+        let stat = unsafe { stat.assume_init() };
+        if stat.st_mode & libc::S_IFMT != libc::S_IFCHR || libc::major(stat.st_rdev) != DRM_MAJOR {
+            return None;
+        }
+        match libc::minor(stat.st_rdev) >> 6 {
+            0 => Some(NodeType::Primary),
+            1 => Some(NodeType::Control),
+            2 => Some(NodeType::Render),
+            _ => None,
+        }
+    }
+
+    /// Get a summary of the capabilities of the device, e.g. for bug reports
+    pub fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities {
+            backend_name: self.backend_name().to_owned(),
+            node_type: self.node_type(),
+            fd_for_plane: symbols::BO_GET_FD_FOR_PLANE.is_present(),
+            modifiers: symbols::BO_CREATE_WITH_MODIFIERS.is_present()
+                && symbols::SURFACE_CREATE_WITH_MODIFIERS.is_present(),
+            modifiers2: symbols::BO_CREATE_WITH_MODIFIERS2.is_present()
+                && symbols::SURFACE_CREATE_WITH_MODIFIERS2.is_present(),
+            max_dimensions: self.max_dimensions(),
+        }
+    }
+
+    /// Check whether the DRM device supports framebuffers with explicit modifiers
+    ///
+    /// This queries `DRM_CAP_ADDFB2_MODIFIERS`, which tells whether framebuffers can be
+    /// created with [`FbCmd2Flags::MODIFIERS`](drm::control::FbCmd2Flags::MODIFIERS)
+    /// using `add_planar_framebuffer`. Otherwise only buffers with an implicit modifier
+    /// can be used for scanout, e.g. using `add_framebuffer`.
+    #[cfg(feature = "drm-support")]
+    pub fn supports_addfb2_modifiers(&self) -> IoResult<bool> {
+        DrmFd(self.as_fd())
+            .get_driver_capability(drm::DriverCapability::AddFB2Modifiers)
+            .map(|value| value != 0)
+    }
+
+    /// Get the maximum width and height of scanout buffers supported by the device
+    ///
+    /// The limits are the framebuffer limits reported by the DRM device, which requires the
+    /// `drm-support` feature and a device supporting modesetting (i.e. not a render node).
+    /// They are queried on first use and only enforced for allocations with
+    /// [`BufferObjectFlags::SCANOUT`], as display controllers often support smaller
+    /// buffers than the gpu renders to. Returns `None` if the limits are unknown.
+    pub fn max_dimensions(&self) -> Option<(u32, u32)> {
+        #[cfg(feature = "drm-support")]
+        return *self
+            .max_dimensions
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| DrmFd(self.as_fd()).max_dimensions());
+        #[cfg(not(feature = "drm-support"))]
+        return None;
+    }
+
+    fn check_dimensions(&self, width: u32, height: u32, usage: BufferObjectFlags) -> IoResult<()> {
+        if !usage.contains(BufferObjectFlags::SCANOUT) {
+            return Ok(());
+        }
+        match self.max_dimensions() {
+            Some((max_width, max_height)) if width > max_width || height > max_height => {
+                Err(IoError::new(
+                    ErrorKind::InvalidInput,
+                    TooLargeError {
+                        width,
+                        height,
+                        max_width,
+                        max_height,
+                    },
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check whether the loaded libgbm provides the function `symbol`
+    ///
+    /// Not every version of libgbm provides all functions, e.g.
+    /// `gbm_bo_create_with_modifiers2` was added in Mesa 21.3. Calling a missing function
+    /// aborts the process, so the methods wrapping the `*_with_modifiers` and
+    /// `*_with_modifiers2` functions check for their presence first and fail with
+    /// [`ErrorKind::Unsupported`], allowing callers to fall back to older variants.
+    ///
+    /// This only helps if the executable was linked with lazy binding, otherwise
+    /// loading it already fails on a libgbm missing any of the used functions.
+    pub fn has_symbol(&self, symbol: &str) -> bool {
+        symbols::has_symbol(symbol)
+    }
+
     /// Test if a format is supported for a given set of usage flags
     pub fn is_format_supported(&self, format: Format, usage: BufferObjectFlags) -> bool {
         unsafe { ffi::gbm_device_is_format_supported(*self.ffi, format as u32, usage.bits()) != 0 }
     }
 
+    /// Test if a format is supported with a given modifier and set of usage flags
+    ///
+    /// libgbm has no query for this, so this attempts to allocate a 1x1 buffer object
+    /// with the given parameters, which is freed again immediately. The probe bypasses
+    /// the observer set with [`Self::set_alloc_observer()`] and the handler set with
+    /// [`Self::set_oom_handler()`].
+    ///
+    /// Successful probes and definitive rejections (`EINVAL` or `EOPNOTSUPP`) are cached
+    /// per device, so repeated queries don't cause further allocations. Other failures,
+    /// e.g. for lack of memory, or a libgbm missing `gbm_bo_create_with_modifiers2`
+    /// report the combination as unsupported without caching the result.
+    pub fn is_format_modifier_supported(
+        &self,
+        format: Format,
+        modifier: Modifier,
+        usage: BufferObjectFlags,
+    ) -> bool {
+        let key = (format, modifier, usage);
+        if let Some(supported) = self.modifier_support.lock().unwrap().get(&key) {
+            return *supported;
+        }
+        if symbols::BO_CREATE_WITH_MODIFIERS2.require().is_err() {
+            return false;
+        }
+        let modifier: u64 = modifier.into();
+        crate::surface::clear_errno();
+        let ptr = unsafe {
+            ffi::gbm_bo_create_with_modifiers2(
+                *self.ffi,
+                1,
+                1,
+                format as u32,
+                &modifier,
+                1,
+                usage.bits(),
+            )
+        };
+        if ptr.is_null() {
+            let errno = IoError::last_os_error().raw_os_error();
+            if matches!(errno, Some(libc::EINVAL) | Some(libc::EOPNOTSUPP)) {
+                self.modifier_support.lock().unwrap().insert(key, false);
+            }
+            return false;
+        }
+        unsafe { ffi::gbm_bo_destroy(ptr) };
+        self.modifier_support.lock().unwrap().insert(key, true);
+        true
+    }
+
     /// Get the required number of planes for a given format and modifier
     ///
     /// Some combination (e.g. when using a `Modifier::Invalid`) might not
@@ -127,14 +722,41 @@ impl<T: AsFd> Device<T> {
         format: Format,
         usage: BufferObjectFlags,
     ) -> IoResult<Surface<U>> {
-        let ptr = unsafe {
+        self.check_dimensions(width, height, usage)?;
+        let usage = self.default_usage(usage);
+        let ptr = self.alloc_with_retry(|| unsafe {
             ffi::gbm_surface_create(*self.ffi, width, height, format as u32, usage.bits())
-        };
-        if ptr.is_null() {
-            Err(IoError::last_os_error())
-        } else {
-            Ok(unsafe { Surface::new(ptr, self.ffi.clone()) })
-        }
+        })?;
+        Ok(
+            unsafe { Surface::new(ptr, self.ffi.clone(), self.raw_fd, width, height, format) }
+                .with_creation_params(Some(usage), None),
+        )
+    }
+
+    /// Allocate a new surface object for scanout in the first supported default format
+    ///
+    /// The formats are tried in order of preference: [`Format::Xrgb8888`], which every
+    /// KMS driver supports for its primary planes, followed by [`Format::Argb8888`]. The
+    /// first format [supported](Self::is_format_supported()) with
+    /// [`BufferObjectFlags::SCANOUT`] and [`BufferObjectFlags::RENDERING`] is used.
+    /// Fails with [`ErrorKind::Unsupported`] if neither is supported.
+    pub fn create_default_scanout_surface<U: 'static>(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> IoResult<Surface<U>> {
+        const FORMATS: [Format; 2] = [Format::Xrgb8888, Format::Argb8888];
+        let usage = BufferObjectFlags::SCANOUT | BufferObjectFlags::RENDERING;
+        let format = FORMATS
+            .into_iter()
+            .find(|format| self.is_format_supported(*format, usage))
+            .ok_or_else(|| {
+                IoError::new(
+                    ErrorKind::Unsupported,
+                    "Neither XRGB8888 nor ARGB8888 is supported for scanout",
+                )
+            })?;
+        self.create_surface(width, height, format, usage)
     }
 
     /// Allocate a new surface object with explicit modifiers
@@ -145,8 +767,10 @@ impl<T: AsFd> Device<T> {
         format: Format,
         modifiers: impl Iterator<Item = Modifier>,
     ) -> IoResult<Surface<U>> {
-        let mods = modifiers.map(|m| m.into()).collect::<Vec<u64>>();
-        let ptr = unsafe {
+        symbols::SURFACE_CREATE_WITH_MODIFIERS.require()?;
+        let modifiers = modifiers.collect::<Vec<_>>();
+        let mods = modifiers.iter().map(|m| (*m).into()).collect::<Vec<u64>>();
+        let ptr = self.alloc_with_retry(|| unsafe {
             ffi::gbm_surface_create_with_modifiers(
                 *self.ffi,
                 width,
@@ -155,12 +779,37 @@ impl<T: AsFd> Device<T> {
                 mods.as_ptr(),
                 mods.len() as u32,
             )
-        };
-        if ptr.is_null() {
-            Err(IoError::last_os_error())
-        } else {
-            Ok(unsafe { Surface::new(ptr, self.ffi.clone()) })
+        })?;
+        Ok(
+            unsafe { Surface::new(ptr, self.ffi.clone(), self.raw_fd, width, height, format) }
+                .with_creation_params(None, Some(modifiers)),
+        )
+    }
+
+    /// Allocate a new surface object with explicit modifiers, verifying modifier support first
+    ///
+    /// Unlike [`Self::create_surface_with_modifiers()`] this checks that the device supports
+    /// at least one of the given modifiers for `format` (see [`Self::format_modifier_plane_count()`])
+    /// before calling into libgbm. If none are supported an error of kind
+    /// [`ErrorKind::Unsupported`] wrapping an [`UnsupportedModifiersError`] is returned.
+    pub fn create_surface_with_modifiers_checked<U: 'static>(
+        &self,
+        width: u32,
+        height: u32,
+        format: Format,
+        modifiers: impl Iterator<Item = Modifier>,
+    ) -> IoResult<Surface<U>> {
+        let modifiers = modifiers.collect::<Vec<_>>();
+        if !modifiers.iter().any(|modifier| {
+            self.format_modifier_plane_count(format, *modifier)
+                .is_some()
+        }) {
+            return Err(IoError::new(
+                ErrorKind::Unsupported,
+                UnsupportedModifiersError { format, modifiers },
+            ));
         }
+        self.create_surface_with_modifiers(width, height, format, modifiers.into_iter())
     }
 
     /// Allocate a new surface object with explicit modifiers and flags
@@ -172,8 +821,11 @@ impl<T: AsFd> Device<T> {
         modifiers: impl Iterator<Item = Modifier>,
         usage: BufferObjectFlags,
     ) -> IoResult<Surface<U>> {
-        let mods = modifiers.map(|m| m.into()).collect::<Vec<u64>>();
-        let ptr = unsafe {
+        self.check_dimensions(width, height, usage)?;
+        symbols::SURFACE_CREATE_WITH_MODIFIERS2.require()?;
+        let modifiers = modifiers.collect::<Vec<_>>();
+        let mods = modifiers.iter().map(|m| (*m).into()).collect::<Vec<u64>>();
+        let ptr = self.alloc_with_retry(|| unsafe {
             ffi::gbm_surface_create_with_modifiers2(
                 *self.ffi,
                 width,
@@ -183,12 +835,11 @@ impl<T: AsFd> Device<T> {
                 mods.len() as u32,
                 usage.bits(),
             )
-        };
-        if ptr.is_null() {
-            Err(IoError::last_os_error())
-        } else {
-            Ok(unsafe { Surface::new(ptr, self.ffi.clone()) })
-        }
+        })?;
+        Ok(
+            unsafe { Surface::new(ptr, self.ffi.clone(), self.raw_fd, width, height, format) }
+                .with_creation_params(Some(usage), Some(modifiers)),
+        )
     }
 
     ///  Allocate a buffer object for the given dimensions
@@ -199,13 +850,12 @@ impl<T: AsFd> Device<T> {
         format: Format,
         usage: BufferObjectFlags,
     ) -> IoResult<BufferObject<U>> {
-        let ptr =
-            unsafe { ffi::gbm_bo_create(*self.ffi, width, height, format as u32, usage.bits()) };
-        if ptr.is_null() {
-            Err(IoError::last_os_error())
-        } else {
-            Ok(unsafe { BufferObject::new(ptr, self.ffi.clone()) })
-        }
+        self.check_dimensions(width, height, usage)?;
+        let usage = self.default_usage(usage);
+        let ptr = self.observe_alloc(width, height, format, None, Some(usage), || unsafe {
+            ffi::gbm_bo_create(*self.ffi, width, height, format as u32, usage.bits())
+        })?;
+        Ok(unsafe { BufferObject::new(ptr, self.ffi.clone(), self.raw_fd, Some(usage)) })
     }
 
     ///  Allocate a buffer object for the given dimensions with explicit modifiers
@@ -216,8 +866,10 @@ impl<T: AsFd> Device<T> {
         format: Format,
         modifiers: impl Iterator<Item = Modifier>,
     ) -> IoResult<BufferObject<U>> {
-        let mods = modifiers.map(|m| m.into()).collect::<Vec<u64>>();
-        let ptr = unsafe {
+        symbols::BO_CREATE_WITH_MODIFIERS.require()?;
+        let modifiers = modifiers.collect::<Vec<Modifier>>();
+        let mods = modifiers.iter().map(|m| (*m).into()).collect::<Vec<u64>>();
+        let ptr = self.observe_alloc(width, height, format, Some(&modifiers), None, || unsafe {
             ffi::gbm_bo_create_with_modifiers(
                 *self.ffi,
                 width,
@@ -226,12 +878,9 @@ impl<T: AsFd> Device<T> {
                 mods.as_ptr(),
                 mods.len() as u32,
             )
-        };
-        if ptr.is_null() {
-            Err(IoError::last_os_error())
-        } else {
-            Ok(unsafe { BufferObject::new(ptr, self.ffi.clone()) })
-        }
+        })?;
+        let bo = unsafe { BufferObject::new(ptr, self.ffi.clone(), self.raw_fd, None) };
+        Ok(bo.with_requested_modifiers(modifiers))
     }
 
     ///  Allocate a buffer object for the given dimensions with explicit modifiers and flags
@@ -243,22 +892,453 @@ impl<T: AsFd> Device<T> {
         modifiers: impl Iterator<Item = Modifier>,
         usage: BufferObjectFlags,
     ) -> IoResult<BufferObject<U>> {
-        let mods = modifiers.map(|m| m.into()).collect::<Vec<u64>>();
-        let ptr = unsafe {
-            ffi::gbm_bo_create_with_modifiers2(
-                *self.ffi,
+        self.check_dimensions(width, height, usage)?;
+        symbols::BO_CREATE_WITH_MODIFIERS2.require()?;
+        let modifiers = modifiers.collect::<Vec<Modifier>>();
+        let mods = modifiers.iter().map(|m| (*m).into()).collect::<Vec<u64>>();
+        let ptr = self.observe_alloc(
+            width,
+            height,
+            format,
+            Some(&modifiers),
+            Some(usage),
+            || unsafe {
+                ffi::gbm_bo_create_with_modifiers2(
+                    *self.ffi,
+                    width,
+                    height,
+                    format as u32,
+                    mods.as_ptr(),
+                    mods.len() as u32,
+                    usage.bits(),
+                )
+            },
+        )?;
+        let bo = unsafe { BufferObject::new(ptr, self.ffi.clone(), self.raw_fd, Some(usage)) };
+        Ok(bo.with_requested_modifiers(modifiers))
+    }
+
+    /// Set an observer called after every buffer object allocation
+    ///
+    /// The observer is passed the parameters of the allocation and the time spent in
+    /// libgbm, e.g. to log slow allocations. It is called for allocations through
+    /// [`Self::create_buffer_object()`], [`Self::create_buffer_object_with_modifiers()`]
+    /// and [`Self::create_buffer_object_with_modifiers2()`] and the methods built on them.
+    ///
+    /// Clones of the device made afterwards share the observer. Without an observer
+    /// allocations aren't timed.
+    pub fn set_alloc_observer(
+        &mut self,
+        observer: impl Fn(&AllocEvent<'_>) + Send + Sync + 'static,
+    ) {
+        self.alloc_observer = Some(Arc::new(observer));
+    }
+
+    /// Remove the observer set with [`Self::set_alloc_observer()`]
+    pub fn clear_alloc_observer(&mut self) {
+        self.alloc_observer = None;
+    }
+
+    /// Set a handler called when an allocation fails for lack of memory
+    ///
+    /// If allocating a buffer object or surface through this device fails with `ENOMEM`,
+    /// the handler is called, e.g. to evict caches of buffers. If it returns `true`,
+    /// indicating that memory was freed, the allocation is retried once. Otherwise the
+    /// error is returned.
+    ///
+    /// [`Surface::recreate()`] allocates without the device and isn't retried, nor are
+    /// the probes of [`Self::is_format_modifier_supported()`].
+    ///
+    /// Clones of the device made afterwards share the handler.
+    pub fn set_oom_handler(&mut self, handler: impl Fn() -> bool + Send + Sync + 'static) {
+        self.oom_handler = Some(Arc::new(handler));
+    }
+
+    /// Remove the handler set with [`Self::set_oom_handler()`]
+    pub fn clear_oom_handler(&mut self) {
+        self.oom_handler = None;
+    }
+
+    // Runs `alloc`, retrying once if it failed with `ENOMEM` and the OOM handler freed memory
+    fn alloc_with_retry<P>(&self, alloc: impl Fn() -> *mut P) -> IoResult<*mut P> {
+        let ptr = alloc();
+        if !ptr.is_null() {
+            return Ok(ptr);
+        }
+        let err = IoError::last_os_error();
+        match &self.oom_handler {
+            Some(handler) if err.raw_os_error() == Some(libc::ENOMEM) && handler() => {
+                let ptr = alloc();
+                if ptr.is_null() {
+                    Err(IoError::last_os_error())
+                } else {
+                    Ok(ptr)
+                }
+            }
+            _ => Err(err),
+        }
+    }
+
+    // Applies the defaults configured with the `DeviceBuilder`
+    fn default_usage(&self, usage: BufferObjectFlags) -> BufferObjectFlags {
+        if self.force_linear {
+            usage | BufferObjectFlags::LINEAR
+        } else {
+            usage
+        }
+    }
+
+    fn observe_alloc(
+        &self,
+        width: u32,
+        height: u32,
+        format: Format,
+        modifiers: Option<&[Modifier]>,
+        usage: Option<BufferObjectFlags>,
+        alloc: impl Fn() -> *mut ffi::gbm_bo,
+    ) -> IoResult<*mut ffi::gbm_bo> {
+        let start = self.alloc_observer.as_ref().map(|_| Instant::now());
+        // Capture the error before the observer can clobber errno
+        let result = self.alloc_with_retry(alloc);
+        if let (Some(observer), Some(start)) = (&self.alloc_observer, start) {
+            observer(&AllocEvent {
                 width,
                 height,
-                format as u32,
-                mods.as_ptr(),
-                mods.len() as u32,
-                usage.bits(),
-            )
+                format,
+                modifiers,
+                usage,
+                elapsed: start.elapsed(),
+                success: result.is_ok(),
+            });
+        }
+        result
+    }
+
+    /// Allocate a buffer object, that is accessible by the CPU
+    ///
+    /// This allocates a buffer object with [`BufferObjectFlags::LINEAR`] and
+    /// [`BufferObjectFlags::WRITE`], so it can be reliably mapped with
+    /// [`BufferObject::map()`] for readback and written to with
+    /// [`BufferObject::write()`]. Use [`Device::create_buffer_object()`] with these flags
+    /// and additional ones (e.g. [`BufferObjectFlags::RENDERING`]) if the buffer is also
+    /// used by the GPU, keeping in mind that drivers might not support every combination.
+    pub fn create_mappable_buffer<U: 'static>(
+        &self,
+        width: u32,
+        height: u32,
+        format: Format,
+    ) -> IoResult<BufferObject<U>> {
+        self.create_buffer_object(
+            width,
+            height,
+            format,
+            BufferObjectFlags::LINEAR | BufferObjectFlags::WRITE,
+        )
+    }
+
+    /// Allocate a buffer object with the same layout as `template`
+    ///
+    /// The buffer object is allocated with the width, height, format and modifier of
+    /// `template` using [`Self::create_buffer_object_with_modifiers2()`], e.g. for the
+    /// second buffer of a ping-pong pair. If the modifier of `template` is
+    /// `Modifier::Invalid`, i.e. it uses an implicit layout, the buffer object is
+    /// allocated with [`Self::create_buffer_object()`] instead, which doesn't guarantee
+    /// the same layout.
+    pub fn create_matching<X: 'static, U: 'static>(
+        &self,
+        template: &BufferObject<X>,
+        usage: BufferObjectFlags,
+    ) -> IoResult<BufferObject<U>> {
+        let (width, height, format) = (template.width(), template.height(), template.format());
+        match template.modifier() {
+            Modifier::Invalid => self.create_buffer_object(width, height, format, usage),
+            modifier => self.create_buffer_object_with_modifiers2(
+                width,
+                height,
+                format,
+                std::iter::once(modifier),
+                usage,
+            ),
+        }
+    }
+
+    /// Allocate a buffer object with the first allocatable format and modifiers
+    ///
+    /// Tries every format with its list of modifiers in order, returning the first buffer
+    /// object allocated successfully together with the chosen format and modifier. An empty
+    /// list of modifiers allocates with [`Self::create_buffer_object()`], leaving the
+    /// layout to the driver, otherwise [`Self::create_buffer_object_with_modifiers2()`]
+    /// is used.
+    ///
+    /// Fails with the error of the last candidate if none could be allocated, or with
+    /// [`ErrorKind::InvalidInput`] if `candidates` is empty.
+    pub fn negotiate_and_create<U: 'static>(
+        &self,
+        width: u32,
+        height: u32,
+        candidates: &[(Format, Vec<Modifier>)],
+        usage: BufferObjectFlags,
+    ) -> IoResult<NegotiatedBuffer<U>> {
+        let mut last_err = None;
+        for (format, modifiers) in candidates {
+            let result = if modifiers.is_empty() {
+                self.create_buffer_object(width, height, *format, usage)
+            } else {
+                self.create_buffer_object_with_modifiers2(
+                    width,
+                    height,
+                    *format,
+                    modifiers.iter().copied(),
+                    usage,
+                )
+            };
+            match result {
+                Ok(buffer) => {
+                    return Ok(NegotiatedBuffer {
+                        format: *format,
+                        modifier: buffer.modifier(),
+                        buffer,
+                    })
+                }
+                Err(err) => {
+                    log::debug!(
+                        "Failed to allocate {:?} buffer with modifiers {:?}: {}",
+                        format,
+                        modifiers,
+                        err
+                    );
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err
+            .unwrap_or_else(|| IoError::new(ErrorKind::InvalidInput, "No format candidates given")))
+    }
+
+    /// Allocate a buffer object in protected memory, e.g. for HDCP protected content
+    ///
+    /// This adds [`BufferObjectFlags::PROTECTED`] to `usage`. Protected buffer objects can
+    /// only be accessed by the gpu and display hardware: mapping them or writing to them
+    /// with [`BufferObject::write()`] fails with [`MapError::Protected`](crate::MapError::Protected).
+    /// Allocation fails on drivers not supporting protected memory.
+    pub fn create_protected_buffer<U: 'static>(
+        &self,
+        width: u32,
+        height: u32,
+        format: Format,
+        usage: BufferObjectFlags,
+    ) -> IoResult<BufferObject<U>> {
+        self.create_buffer_object(width, height, format, usage | BufferObjectFlags::PROTECTED)
+    }
+
+    /// Query the stride the driver picks for a buffer object with the given parameters
+    ///
+    /// libgbm provides no way to query the stride without allocating, so this allocates
+    /// a buffer object of the full size, reads its stride and frees it again. This costs
+    /// as much as an actual allocation, including passing through the allocation observer
+    /// and OOM handler, so callers should cache the result for recurring parameters.
+    /// Allocations with different parameters, e.g. modifiers, may result in a different stride.
+    pub fn probe_stride(
+        &self,
+        width: u32,
+        height: u32,
+        format: Format,
+        usage: BufferObjectFlags,
+    ) -> IoResult<u32> {
+        self.create_buffer_object::<()>(width, height, format, usage)
+            .map(|bo| bo.stride())
+    }
+
+    /// Allocate a scanout buffer object and verify KMS can display it
+    ///
+    /// Allocating a buffer with [`BufferObjectFlags::SCANOUT`] doesn't guarantee, that KMS
+    /// accepts it as a framebuffer, e.g. if the chosen modifier isn't supported by any plane.
+    /// This allocates a buffer object with the given modifiers and creates a test framebuffer
+    /// from it, which is immediately removed again. The buffer object is only returned, if
+    /// creating the framebuffer succeeded, otherwise the error of the framebuffer creation
+    /// is returned.
+    ///
+    /// This costs an additional framebuffer creation and removal per allocation and requires
+    /// the device to support modesetting.
+    #[cfg(feature = "drm-support")]
+    pub fn create_scanout_buffer_verified<U: 'static>(
+        &self,
+        width: u32,
+        height: u32,
+        format: Format,
+        modifiers: impl Iterator<Item = Modifier>,
+    ) -> IoResult<BufferObject<U>> {
+        use drm::control::FbCmd2Flags;
+
+        let bo = self.create_buffer_object_with_modifiers2(
+            width,
+            height,
+            format,
+            modifiers,
+            BufferObjectFlags::SCANOUT,
+        )?;
+        let drm = DrmFd(self.as_fd());
+        let flags = if bo.modifier() == Modifier::Invalid {
+            FbCmd2Flags::empty()
+        } else {
+            FbCmd2Flags::MODIFIERS
         };
+        let fb = drm.add_planar_framebuffer(&bo, flags)?;
+        drm.destroy_framebuffer(fb)?;
+        Ok(bo)
+    }
+
+    /// Allocate a scanout buffer object together with a framebuffer for it
+    ///
+    /// The buffer object is allocated with [`BufferObjectFlags::SCANOUT`] and one of the
+    /// given modifiers, if the device supports framebuffers with explicit modifiers (see
+    /// [`Self::supports_addfb2_modifiers()`]), otherwise with an implicit modifier.
+    /// The framebuffer is stored as [`OwnedFramebuffer`] in the userdata of the buffer
+    /// object, which removes it once the buffer object is dropped.
+    ///
+    /// Requires the device to support modesetting.
+    #[cfg(feature = "drm-support")]
+    pub fn create_framebuffer_buffer(
+        &self,
+        width: u32,
+        height: u32,
+        format: Format,
+        modifiers: impl Iterator<Item = Modifier>,
+    ) -> IoResult<(
+        BufferObject<OwnedFramebuffer>,
+        drm::control::framebuffer::Handle,
+    )> {
+        use drm::control::FbCmd2Flags;
+
+        let explicit_modifiers = self.supports_addfb2_modifiers().unwrap_or(false);
+        let mut bo = if explicit_modifiers {
+            self.create_buffer_object_with_modifiers2(
+                width,
+                height,
+                format,
+                modifiers,
+                BufferObjectFlags::SCANOUT,
+            )?
+        } else {
+            self.create_buffer_object(width, height, format, BufferObjectFlags::SCANOUT)?
+        };
+        let flags = if explicit_modifiers && bo.modifier() != Modifier::Invalid {
+            FbCmd2Flags::MODIFIERS
+        } else {
+            FbCmd2Flags::empty()
+        };
+        // Keep the device open until the framebuffer is removed
+        let fd = self.as_fd().try_clone_to_owned()?;
+        let handle = DrmFd(self.as_fd()).add_planar_framebuffer(&bo, flags)?;
+        bo.set_userdata(OwnedFramebuffer { handle, fd });
+        Ok((bo, handle))
+    }
+
+    /// Create a GBM buffer object from any supported [`ImportSource`]
+    ///
+    /// This is a unified entry point for the typed `import_buffer_object_from_*` methods,
+    /// which delegate to it.
+    ///
+    /// The GBM bo shares the underlying pixels but its life-time is
+    /// independent of the foreign object.
+    pub fn import<U: 'static>(
+        &self,
+        source: ImportSource<'_>,
+        usage: BufferObjectFlags,
+    ) -> IoResult<BufferObject<U>> {
+        match source {
+            #[cfg(feature = "import-wayland")]
+            ImportSource::WlBuffer(buffer) => {
+                use wayland_server::Resource;
+
+                unsafe {
+                    self.import_raw(
+                        ffi::GBM_BO_IMPORT_WL_BUFFER,
+                        buffer.id().as_ptr() as *mut _,
+                        usage,
+                    )
+                }
+            }
+            #[cfg(feature = "import-egl")]
+            ImportSource::EglImage(image) => unsafe {
+                self.import_raw(ffi::GBM_BO_IMPORT_EGL_IMAGE, image.as_ptr(), usage)
+            },
+            ImportSource::Dmabuf {
+                fd,
+                width,
+                height,
+                stride,
+                format,
+            } => {
+                let mut fd_data = ffi::gbm_import_fd_data {
+                    fd: fd.as_raw_fd(),
+                    width,
+                    height,
+                    stride,
+                    format: format as u32,
+                };
+                unsafe {
+                    self.import_raw(
+                        ffi::GBM_BO_IMPORT_FD,
+                        &mut fd_data as *mut ffi::gbm_import_fd_data as *mut _,
+                        usage,
+                    )
+                }
+            }
+            ImportSource::DmabufModifier {
+                len,
+                fds,
+                width,
+                height,
+                format,
+                strides,
+                offsets,
+                modifier,
+            } => {
+                check_plane_count(
+                    len,
+                    &fds,
+                    self.format_modifier_plane_count(format, modifier),
+                )?;
+                let mut fd_data = ffi::gbm_import_fd_modifier_data {
+                    fds: fds.map(|fd| fd.map_or(-1, |x| x.as_raw_fd())),
+                    width,
+                    height,
+                    format: format as u32,
+                    strides,
+                    offsets,
+                    modifier: modifier.into(),
+                    num_fds: len,
+                };
+                unsafe {
+                    self.import_raw(
+                        ffi::GBM_BO_IMPORT_FD_MODIFIER,
+                        &mut fd_data as *mut ffi::gbm_import_fd_modifier_data as *mut _,
+                        usage,
+                    )
+                }
+            }
+        }
+    }
+
+    unsafe fn import_raw<U: 'static>(
+        &self,
+        type_: u32,
+        buffer: *mut libc::c_void,
+        usage: BufferObjectFlags,
+    ) -> IoResult<BufferObject<U>> {
+        let ptr = ffi::gbm_bo_import(*self.ffi, type_, buffer, usage.bits());
         if ptr.is_null() {
             Err(IoError::last_os_error())
         } else {
-            Ok(unsafe { BufferObject::new(ptr, self.ffi.clone()) })
+            let bo = BufferObject::new(ptr, self.ffi.clone(), self.raw_fd, Some(usage));
+            if usage.contains(BufferObjectFlags::SCANOUT) && !bo.is_scanout_aligned() {
+                log::debug!(
+                    "Imported scanout buffer has plane offsets {:?}, which are not page-aligned",
+                    bo.planes().map(|plane| plane.offset).collect::<Vec<_>>()
+                );
+            }
+            Ok(bo)
         }
     }
 
@@ -276,21 +1356,7 @@ impl<T: AsFd> Device<T> {
         buffer: &WlBuffer,
         usage: BufferObjectFlags,
     ) -> IoResult<BufferObject<U>> {
-        use wayland_server::Resource;
-
-        let ptr = unsafe {
-            ffi::gbm_bo_import(
-                *self.ffi,
-                ffi::GBM_BO_IMPORT_WL_BUFFER,
-                buffer.id().as_ptr() as *mut _,
-                usage.bits(),
-            )
-        };
-        if ptr.is_null() {
-            Err(IoError::last_os_error())
-        } else {
-            Ok(unsafe { BufferObject::new(ptr, self.ffi.clone()) })
-        }
+        self.import(ImportSource::WlBuffer(buffer), usage)
     }
 
     /// Create a GBM buffer object from an egl buffer
@@ -304,25 +1370,18 @@ impl<T: AsFd> Device<T> {
     ///
     /// # Safety
     ///
-    /// The given [`EGLImage`] is a raw pointer.  Passing null or an invalid [`EGLImage`] will
-    /// cause undefined behavior.
+    /// The given [`EGLImage`] must be null or a valid `EGLImage`. Passing an invalid
+    /// image will cause undefined behavior, a null image is rejected with an error of
+    /// kind [`ErrorKind::InvalidInput`].
     #[cfg(feature = "import-egl")]
     pub unsafe fn import_buffer_object_from_egl<U: 'static>(
         &self,
         buffer: EGLImage,
         usage: BufferObjectFlags,
     ) -> IoResult<BufferObject<U>> {
-        let ptr = ffi::gbm_bo_import(
-            *self.ffi,
-            ffi::GBM_BO_IMPORT_EGL_IMAGE,
-            buffer,
-            usage.bits(),
-        );
-        if ptr.is_null() {
-            Err(IoError::last_os_error())
-        } else {
-            Ok(BufferObject::new(ptr, self.ffi.clone()))
-        }
+        let image = EglImageSource::new(buffer)
+            .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, "EGLImage is null"))?;
+        self.import(ImportSource::EglImage(image), usage)
     }
 
     /// Create a GBM buffer object from a dma buffer
@@ -342,27 +1401,42 @@ impl<T: AsFd> Device<T> {
         format: Format,
         usage: BufferObjectFlags,
     ) -> IoResult<BufferObject<U>> {
-        let mut fd_data = ffi::gbm_import_fd_data {
-            fd: buffer.as_raw_fd(),
-            width,
-            height,
-            stride,
-            format: format as u32,
-        };
+        self.import(
+            ImportSource::Dmabuf {
+                fd: buffer,
+                width,
+                height,
+                stride,
+                format,
+            },
+            usage,
+        )
+    }
 
-        let ptr = unsafe {
-            ffi::gbm_bo_import(
-                *self.ffi,
-                ffi::GBM_BO_IMPORT_FD,
-                &mut fd_data as *mut ffi::gbm_import_fd_data as *mut _,
-                usage.bits(),
-            )
-        };
-        if ptr.is_null() {
-            Err(IoError::last_os_error())
-        } else {
-            Ok(unsafe { BufferObject::new(ptr, self.ffi.clone()) })
-        }
+    /// Create a GBM buffer object from a GEM handle of this device
+    ///
+    /// libgbm can't import GEM handles directly, so this first exports the handle as
+    /// dma buffer (`drmPrimeHandleToFD`) and imports that with
+    /// [`Self::import_buffer_object_from_dma_buf()`]. The intermediate file descriptor
+    /// is closed again after the import.
+    ///
+    /// The GBM bo shares the underlying pixels but its life-time is
+    /// independent of the GEM handle.
+    #[cfg(feature = "drm-support")]
+    pub fn import_gem_handle<U: 'static>(
+        &self,
+        handle: u32,
+        width: u32,
+        height: u32,
+        stride: u32,
+        format: Format,
+        usage: BufferObjectFlags,
+    ) -> IoResult<BufferObject<U>> {
+        let handle = std::num::NonZeroU32::new(handle)
+            .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, "invalid GEM handle 0"))?;
+        let fd = DrmFd(self.as_fd())
+            .buffer_to_prime_fd(handle.into(), (libc::O_CLOEXEC | libc::O_RDWR) as u32)?;
+        self.import_buffer_object_from_dma_buf(fd.as_fd(), width, height, stride, format, usage)
     }
 
     /// Create a GBM buffer object from a dma buffer with explicit modifiers
@@ -386,31 +1460,190 @@ impl<T: AsFd> Device<T> {
         offsets: [i32; 4],
         modifier: Modifier,
     ) -> IoResult<BufferObject<U>> {
-        let fds = buffers.map(|fd| fd.map_or(-1, |x| x.as_raw_fd()));
-        let mut fd_data = ffi::gbm_import_fd_modifier_data {
-            fds,
-            width,
-            height,
-            format: format as u32,
-            strides,
-            offsets,
-            modifier: modifier.into(),
-            num_fds: len,
+        self.import(
+            ImportSource::DmabufModifier {
+                len,
+                fds: buffers,
+                width,
+                height,
+                format,
+                strides,
+                offsets,
+                modifier,
+            },
+            usage,
+        )
+    }
+
+    /// Create a GBM buffer object from a dma buffer containing all planes
+    ///
+    /// Many video decoders output multi-planar buffers, e.g. [`Format::Nv12`], as a single
+    /// dma buffer with the planes at different offsets. This imports such a buffer by
+    /// passing `fd` for every plane described by its offset and stride in `planes`.
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] if the number of planes doesn't match the
+    /// format as described by [`planar_info()`](crate::planar_info()). Modifiers other
+    /// than `Modifier::Linear` and `Modifier::Invalid` may add auxiliary planes, e.g.
+    /// for compression metadata, so for them additional planes are accepted.
+    #[allow(clippy::too_many_arguments)]
+    pub fn import_single_fd_planar<U: 'static>(
+        &self,
+        fd: BorrowedFd<'_>,
+        width: u32,
+        height: u32,
+        format: Format,
+        modifier: Modifier,
+        planes: &[(u32, u32)],
+        usage: BufferObjectFlags,
+    ) -> IoResult<BufferObject<U>> {
+        let expected = crate::planar_info(format).map_or(1, |info| info.planes.len());
+        let valid = match modifier {
+            Modifier::Linear | Modifier::Invalid => planes.len() == expected,
+            _ => planes.len() >= expected,
         };
+        if !valid {
+            return Err(IoError::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{:?} buffers have {} planes, got {}",
+                    format,
+                    expected,
+                    planes.len()
+                ),
+            ));
+        }
+        let source = planes
+            .iter()
+            .fold(
+                DmabufImportBuilder::new()
+                    .dimensions(width, height)
+                    .format(format)
+                    .modifier(modifier),
+                |builder, &(offset, stride)| builder.plane(fd, offset, stride),
+            )
+            .build()?;
+        self.import(source, usage)
+    }
 
-        let ptr = unsafe {
-            ffi::gbm_bo_import(
-                *self.ffi,
-                ffi::GBM_BO_IMPORT_FD_MODIFIER,
-                &mut fd_data as *mut ffi::gbm_import_fd_modifier_data as *mut _,
-                usage.bits(),
+    /// Import a shared memory file, e.g. a memfd of a software rendering client
+    ///
+    /// The file is imported like a single plane dma buffer with a linear layout, which
+    /// allows handing the memory of shm-based clients to APIs consuming buffer objects
+    /// without copying. Whether this works depends on the backend and kernel driver: it
+    /// requires a driver importing arbitrary file descriptors as dma buffers, which
+    /// most don't, e.g. Mesa's drivers only accept real dma buffers. Failed imports are
+    /// returned with a message pointing at the possibly missing support.
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] if the file is smaller than `height`
+    /// rows of `stride` bytes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn import_from_memfd<U: 'static>(
+        &self,
+        fd: BorrowedFd<'_>,
+        width: u32,
+        height: u32,
+        stride: u32,
+        format: Format,
+        usage: BufferObjectFlags,
+    ) -> IoResult<BufferObject<U>> {
+        let size = fs::File::from(fd.try_clone_to_owned()?).metadata()?.len();
+        if size < stride as u64 * height as u64 {
+            return Err(IoError::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "shared memory of {} bytes is too small for {} rows of {} bytes",
+                    size, height, stride
+                ),
+            ));
+        }
+        let source = DmabufImportBuilder::new()
+            .dimensions(width, height)
+            .format(format)
+            .modifier(Modifier::Linear)
+            .plane(fd, 0, stride)
+            .build()?;
+        self.import(source, usage).map_err(|err| {
+            IoError::new(
+                err.kind(),
+                format!(
+                    "failed to import shared memory, the driver might not support importing it as dma buffer: {}",
+                    err
+                ),
             )
-        };
-        if ptr.is_null() {
-            Err(IoError::last_os_error())
-        } else {
-            Ok(unsafe { BufferObject::new(ptr, self.ffi.clone()) })
+        })
+    }
+}
+
+// Validate the planes of a dma buffer import against the number of planes libgbm
+// expects for its format and modifier, if known. Drivers read `len` planes regardless.
+fn check_plane_count(
+    len: u32,
+    fds: &[Option<BorrowedFd<'_>>; 4],
+    expected: Option<u32>,
+) -> IoResult<()> {
+    if len == 0 || len as usize > fds.len() {
+        return Err(IoError::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "dma buffers need to have between one and four planes, got {}",
+                len
+            ),
+        ));
+    }
+    if fds[..len as usize].iter().any(Option::is_none) {
+        return Err(IoError::new(
+            ErrorKind::InvalidInput,
+            format!("missing file descriptor for one of the {} planes", len),
+        ));
+    }
+    match expected {
+        Some(expected) if expected != len => Err(IoError::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "format and modifier require {} planes, got {}",
+                expected, len
+            ),
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Minimal drm-rs device over a borrowed fd, to issue ioctls independently of `T`
+#[cfg(feature = "drm-support")]
+struct DrmFd<'a>(BorrowedFd<'a>);
+
+#[cfg(feature = "drm-support")]
+impl<'a> AsFd for DrmFd<'a> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0
+    }
+}
+
+#[cfg(feature = "drm-support")]
+impl<'a> DrmDevice for DrmFd<'a> {}
+
+#[cfg(feature = "drm-support")]
+impl<'a> DrmControlDevice for DrmFd<'a> {}
+
+#[cfg(feature = "drm-support")]
+impl<'a> DrmFd<'a> {
+    fn max_dimensions(&self) -> Option<(u32, u32)> {
+        use std::ops::{Bound, RangeBounds};
+
+        fn max(range: impl RangeBounds<u32>) -> Option<u32> {
+            match range.end_bound() {
+                Bound::Included(max) => Some(*max),
+                Bound::Excluded(max) => max.checked_sub(1),
+                Bound::Unbounded => None,
+            }
         }
+
+        let resources = self.resource_handles().ok()?;
+        Some((
+            max(resources.supported_fb_width())?,
+            max(resources.supported_fb_height())?,
+        ))
+        .filter(|&(width, height)| width > 0 && height > 0)
     }
 }
 
@@ -419,3 +1652,201 @@ impl<T: DrmDevice + AsFd> DrmDevice for Device<T> {}
 
 #[cfg(feature = "drm-support")]
 impl<T: DrmControlDevice + AsFd> DrmControlDevice for Device<T> {}
+
+/// None of the requested modifiers are supported by the device for the given format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedModifiersError {
+    /// The requested format
+    pub format: Format,
+    /// The rejected modifiers
+    pub modifiers: Vec<Modifier>,
+}
+
+impl fmt::Display for UnsupportedModifiersError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Format {:?} is not supported with any of the modifiers {:?}",
+            self.format, self.modifiers
+        )
+    }
+}
+
+impl error::Error for UnsupportedModifiersError {}
+
+/// A framebuffer removed on drop, see [`Device::create_framebuffer_buffer()`]
+#[cfg(feature = "drm-support")]
+#[derive(Debug)]
+pub struct OwnedFramebuffer {
+    handle: drm::control::framebuffer::Handle,
+    fd: std::os::unix::io::OwnedFd,
+}
+
+#[cfg(feature = "drm-support")]
+impl OwnedFramebuffer {
+    /// Handle of the framebuffer
+    pub fn handle(&self) -> drm::control::framebuffer::Handle {
+        self.handle
+    }
+}
+
+#[cfg(feature = "drm-support")]
+impl Drop for OwnedFramebuffer {
+    fn drop(&mut self) {
+        if let Err(err) = DrmFd(self.fd.as_fd()).destroy_framebuffer(self.handle) {
+            log::warn!("Failed to remove framebuffer {:?}: {}", self.handle, err);
+        }
+    }
+}
+
+/// Type of a DRM device node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeType {
+    /// Primary node, e.g. `/dev/dri/card0`
+    Primary,
+    /// Control node
+    Control,
+    /// Render node, e.g. `/dev/dri/renderD128`
+    Render,
+}
+
+/// A buffer object allocated by [`Device::negotiate_and_create()`]
+#[derive(Debug)]
+pub struct NegotiatedBuffer<U: 'static> {
+    /// The allocated buffer object
+    pub buffer: BufferObject<U>,
+    /// The format of the candidate that was allocated
+    pub format: Format,
+    /// The modifier chosen by the driver
+    pub modifier: Modifier,
+}
+
+/// Diagnostic summary of a [`Device`], returned by [`Device::capabilities()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceCapabilities {
+    /// Name of the GBM backend
+    pub backend_name: String,
+    /// Type of the DRM node the device was opened on, if any
+    pub node_type: Option<NodeType>,
+    /// Whether libgbm provides `gbm_bo_get_fd_for_plane`
+    pub fd_for_plane: bool,
+    /// Whether libgbm provides the `*_with_modifiers` functions
+    pub modifiers: bool,
+    /// Whether libgbm provides the `*_with_modifiers2` functions
+    pub modifiers2: bool,
+    /// Maximum dimensions of scanout buffers, if known
+    pub max_dimensions: Option<(u32, u32)>,
+}
+
+impl fmt::Display for DeviceCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "backend: {}", self.backend_name)?;
+        match self.node_type {
+            Some(node_type) => writeln!(f, "node type: {:?}", node_type)?,
+            None => writeln!(f, "node type: none")?,
+        }
+        writeln!(f, "fd_for_plane: {}", self.fd_for_plane)?;
+        writeln!(f, "modifiers: {}", self.modifiers)?;
+        writeln!(f, "modifiers2: {}", self.modifiers2)?;
+        match self.max_dimensions {
+            Some((width, height)) => write!(f, "max dimensions: {}x{}", width, height),
+            None => write!(f, "max dimensions: unknown"),
+        }
+    }
+}
+
+/// The requested dimensions exceed the limits of the device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooLargeError {
+    /// The requested width
+    pub width: u32,
+    /// The requested height
+    pub height: u32,
+    /// The maximum width supported by the device
+    pub max_width: u32,
+    /// The maximum height supported by the device
+    pub max_height: u32,
+}
+
+impl fmt::Display for TooLargeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Dimensions {}x{} exceed the device limits of {}x{}",
+            self.width, self.height, self.max_width, self.max_height
+        )
+    }
+}
+
+impl error::Error for TooLargeError {}
+
+#[cfg(test)]
+mod test {
+    use super::{check_plane_count, DmabufImportBuilder, ImportSource};
+    use crate::{Format, Modifier};
+    use std::os::unix::io::AsFd;
+
+    #[test]
+    fn dmabuf_import_builder() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let source = DmabufImportBuilder::new()
+            .dimensions(64, 32)
+            .format(Format::Nv12)
+            .modifier(Modifier::Linear)
+            .plane(file.as_fd(), 0, 64)
+            .plane(file.as_fd(), 2048, 64)
+            .build()
+            .unwrap();
+        match source {
+            ImportSource::DmabufModifier {
+                len,
+                fds,
+                offsets,
+                strides,
+                modifier,
+                ..
+            } => {
+                assert_eq!(len, 2);
+                assert!(fds[1].is_some() && fds[2].is_none());
+                assert_eq!(offsets, [0, 2048, 0, 0]);
+                assert_eq!(strides, [64, 64, 0, 0]);
+                assert_eq!(modifier, Modifier::Linear);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn dmabuf_import_builder_rejects_invalid() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        assert!(DmabufImportBuilder::new()
+            .format(Format::Argb8888)
+            .plane(file.as_fd(), 0, 256)
+            .build()
+            .is_err());
+        assert!(DmabufImportBuilder::new()
+            .dimensions(64, 64)
+            .format(Format::Argb8888)
+            .build()
+            .is_err());
+        let mut builder = DmabufImportBuilder::new()
+            .dimensions(64, 64)
+            .format(Format::Argb8888);
+        for _ in 0..5 {
+            builder = builder.plane(file.as_fd(), 0, 256);
+        }
+        assert!(builder.build().is_err());
+    }
+
+    #[test]
+    fn plane_count_mismatch() {
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let fds = [Some(file.as_fd()), Some(file.as_fd()), None, None];
+        assert!(check_plane_count(2, &fds, Some(2)).is_ok());
+        assert!(check_plane_count(2, &fds, None).is_ok());
+        let err = check_plane_count(1, &fds, Some(2)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(check_plane_count(3, &fds, None).is_err());
+        assert!(check_plane_count(5, &fds, None).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use crate::BufferObject;
+
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+
+// Userspace API of `linux/dma-buf.h`
+
+const DMA_BUF_SYNC_READ: u32 = 1 << 0;
+const DMA_BUF_SYNC_WRITE: u32 = 1 << 1;
+const DMA_BUF_SYNC_START: u64 = 0 << 2;
+const DMA_BUF_SYNC_END: u64 = 1 << 2;
+
+#[allow(non_camel_case_types)]
+#[repr(C)]
+struct dma_buf_sync {
+    flags: u64,
+}
+
+#[allow(non_camel_case_types)]
+#[repr(C)]
+struct dma_buf_export_sync_file {
+    flags: u32,
+    fd: i32,
+}
+
+#[allow(non_camel_case_types)]
+#[repr(C)]
+struct dma_buf_import_sync_file {
+    flags: u32,
+    fd: i32,
+}
+
+#[cfg(any(
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "sparc",
+    target_arch = "sparc64"
+))]
+mod ioc {
+    pub const WRITE: u64 = 4;
+    pub const READ: u64 = 2;
+    pub const DIR_SHIFT: u64 = 29;
+    pub const SIZE_BITS: u64 = 13;
+}
+
+#[cfg(not(any(
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "sparc",
+    target_arch = "sparc64"
+)))]
+mod ioc {
+    pub const WRITE: u64 = 1;
+    pub const READ: u64 = 2;
+    pub const DIR_SHIFT: u64 = 30;
+    pub const SIZE_BITS: u64 = 14;
+}
+
+// `_IOC` of `asm-generic/ioctl.h` and the architectures overriding it
+const fn ioc(dir: u64, nr: u64, size: usize) -> u64 {
+    let size = size as u64 & ((1 << ioc::SIZE_BITS) - 1);
+    (dir << ioc::DIR_SHIFT) | (size << 16) | ((b'b' as u64) << 8) | nr
+}
+
+const DMA_BUF_IOCTL_SYNC: u64 = ioc(ioc::WRITE, 0, std::mem::size_of::<dma_buf_sync>());
+
+const DMA_BUF_IOCTL_EXPORT_SYNC_FILE: u64 = ioc(
+    ioc::READ | ioc::WRITE,
+    2,
+    std::mem::size_of::<dma_buf_export_sync_file>(),
+);
+
+const DMA_BUF_IOCTL_IMPORT_SYNC_FILE: u64 = ioc(
+    ioc::WRITE,
+    3,
+    std::mem::size_of::<dma_buf_import_sync_file>(),
+);
+
+fn ioctl<D>(fd: BorrowedFd<'_>, request: u64, data: &mut D) -> IoResult<()> {
+    loop {
+        if unsafe { libc::ioctl(fd.as_raw_fd(), request as _, data as *mut D) } == 0 {
+            return Ok(());
+        }
+        let err = IoError::last_os_error();
+        if !matches!(err.kind(), ErrorKind::Interrupted | ErrorKind::WouldBlock) {
+            return Err(err);
+        }
+    }
+}
+
+/// Cpu access to a buffer object bracketed by [`BufferObject::dma_buf_sync_start()`] and
+/// [`BufferObject::dma_buf_sync_end()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DmaBufSyncAccess {
+    /// The cpu only reads the buffer object
+    Read,
+    /// The cpu only writes the buffer object
+    Write,
+    /// The cpu reads and writes the buffer object
+    ReadWrite,
+}
+
+impl DmaBufSyncAccess {
+    fn flags(self) -> u64 {
+        let flags = match self {
+            DmaBufSyncAccess::Read => DMA_BUF_SYNC_READ,
+            DmaBufSyncAccess::Write => DMA_BUF_SYNC_WRITE,
+            DmaBufSyncAccess::ReadWrite => DMA_BUF_SYNC_READ | DMA_BUF_SYNC_WRITE,
+        };
+        flags as u64
+    }
+}
+
+impl<T: 'static> BufferObject<T> {
+    /// Prepare the buffer object for cpu access
+    ///
+    /// This uses `DMA_BUF_IOCTL_SYNC` with `DMA_BUF_SYNC_START` on the DMA-BUF of every
+    /// plane, which waits for pending gpu access and invalidates cpu caches as needed, so
+    /// the cpu observes the current contents. Every call has to be followed by
+    /// [`Self::dma_buf_sync_end()`] with the same `access` once the cpu access finished.
+    ///
+    /// This also counts as synchronization for the read-after-write warning of
+    /// [`Self::map()`] in debug builds.
+    pub fn dma_buf_sync_start(&self, access: DmaBufSyncAccess) -> IoResult<()> {
+        self.dma_buf_sync(DMA_BUF_SYNC_START | access.flags())
+    }
+
+    /// Finish cpu access to the buffer object started with [`Self::dma_buf_sync_start()`]
+    ///
+    /// This uses `DMA_BUF_IOCTL_SYNC` with `DMA_BUF_SYNC_END` on the DMA-BUF of every
+    /// plane, which flushes cpu caches as needed, so cpu writes become visible to the gpu.
+    pub fn dma_buf_sync_end(&self, access: DmaBufSyncAccess) -> IoResult<()> {
+        self.dma_buf_sync(DMA_BUF_SYNC_END | access.flags())
+    }
+
+    fn dma_buf_sync(&self, flags: u64) -> IoResult<()> {
+        for plane in self.planes() {
+            let dmabuf = self.fd_for_plane(plane.index)?;
+            ioctl(
+                dmabuf.as_fd(),
+                DMA_BUF_IOCTL_SYNC,
+                &mut dma_buf_sync { flags },
+            )?;
+        }
+        self.mark_synchronized();
+        Ok(())
+    }
+
+    /// Export the implicit fence of the buffer object as a sync file
+    ///
+    /// This uses `DMA_BUF_IOCTL_EXPORT_SYNC_FILE` on the DMA-BUF of the buffer object to
+    /// obtain a sync file, that signals once all pending writes to the buffer, e.g. by
+    /// rendering, have finished. This allows consumers using explicit synchronization to
+    /// wait for producers relying on implicit synchronization.
+    ///
+    /// If no writes are pending the returned sync file is already signaled.
+    /// Returns `Ok(None)` if the kernel doesn't support exporting sync files (added in Linux 6.0).
+    pub fn acquire_fence(&self) -> IoResult<Option<OwnedFd>> {
+        let dmabuf = self.fd()?;
+        let mut data = dma_buf_export_sync_file {
+            flags: DMA_BUF_SYNC_READ,
+            fd: -1,
+        };
+        match ioctl(dmabuf.as_fd(), DMA_BUF_IOCTL_EXPORT_SYNC_FILE, &mut data) {
+            Ok(()) => Ok(Some(unsafe { OwnedFd::from_raw_fd(data.fd) })),
+            Err(err) if err.raw_os_error() == Some(libc::ENOTTY) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Attach a sync file as implicit fence to the buffer object
+    ///
+    /// This uses `DMA_BUF_IOCTL_IMPORT_SYNC_FILE` on the DMA-BUF of every plane to add
+    /// the fence of `sync_file` as write fence, so that consumers relying on implicit
+    /// synchronization wait for it before reading the buffer. This allows producers using
+    /// explicit synchronization to hand buffers to implicitly synchronized consumers.
+    ///
+    /// Fails with [`ErrorKind::Unsupported`] if the kernel doesn't support importing sync
+    /// files (added in Linux 6.0).
+    pub fn attach_sync_file(&self, sync_file: BorrowedFd<'_>) -> IoResult<()> {
+        for plane in self.planes() {
+            let dmabuf = self.fd_for_plane(plane.index)?;
+            let mut data = dma_buf_import_sync_file {
+                flags: DMA_BUF_SYNC_WRITE,
+                fd: sync_file.as_raw_fd(),
+            };
+            match ioctl(dmabuf.as_fd(), DMA_BUF_IOCTL_IMPORT_SYNC_FILE, &mut data) {
+                Ok(()) => {}
+                Err(err) if err.raw_os_error() == Some(libc::ENOTTY) => {
+                    return Err(IoError::new(
+                        ErrorKind::Unsupported,
+                        "importing sync files into dma buffers is not supported by the kernel",
+                    ))
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn ioctl_numbers() {
+        assert_eq!(super::DMA_BUF_IOCTL_SYNC, 0x4008_6200);
+        assert_eq!(super::DMA_BUF_IOCTL_EXPORT_SYNC_FILE, 0xc008_6202);
+        assert_eq!(super::DMA_BUF_IOCTL_IMPORT_SYNC_FILE, 0x4008_6203);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::{BufferObject, Modifier};
+
+use std::io::Result as IoResult;
+use std::os::unix::io::{AsRawFd, OwnedFd};
+
+/// Signed integer type of EGL attributes
+pub type EGLint = i32;
+
+const EGL_NONE: EGLint = 0x3038;
+const EGL_WIDTH: EGLint = 0x3057;
+const EGL_HEIGHT: EGLint = 0x3056;
+const EGL_LINUX_DRM_FOURCC_EXT: EGLint = 0x3271;
+
+// `EGL_DMA_BUF_PLANE{0..3}_{FD,OFFSET,PITCH}_EXT` and
+// `EGL_DMA_BUF_PLANE{0..3}_MODIFIER_{LO,HI}_EXT`
+const PLANE_ATTRIBUTES: [[EGLint; 5]; 4] = [
+    [0x3272, 0x3273, 0x3274, 0x3443, 0x3444],
+    [0x3275, 0x3276, 0x3277, 0x3445, 0x3446],
+    [0x3278, 0x3279, 0x327A, 0x3447, 0x3448],
+    [0x3440, 0x3441, 0x3442, 0x3449, 0x344A],
+];
+
+/// Attributes to import a [`BufferObject`] into EGL as `EGL_LINUX_DMA_BUF_EXT` image
+///
+/// Holds the exported file descriptors of the planes, which are referenced by the
+/// attributes and have to stay open until `eglCreateImage` returned. EGL duplicates
+/// them, so this can be dropped afterwards.
+///
+/// Created by [`BufferObject::egl_dma_buf_attributes()`].
+#[derive(Debug)]
+pub struct EglDmaBufAttributes {
+    attributes: Vec<(EGLint, EGLint)>,
+    _fds: Vec<OwnedFd>,
+}
+
+impl EglDmaBufAttributes {
+    /// The attributes as key and value pairs, without the terminating `EGL_NONE`
+    pub fn attributes(&self) -> &[(EGLint, EGLint)] {
+        &self.attributes
+    }
+
+    /// The attributes as `EGL_NONE` terminated list for `eglCreateImageKHR`
+    ///
+    /// `eglCreateImage` of EGL 1.5 takes a list of `EGLAttrib` instead, convert the
+    /// values with `as`.
+    pub fn to_attrib_list(&self) -> Vec<EGLint> {
+        self.attributes
+            .iter()
+            .flat_map(|(key, value)| [*key, *value])
+            .chain(std::iter::once(EGL_NONE))
+            .collect()
+    }
+}
+
+impl<T: 'static> BufferObject<T> {
+    /// Export the buffer object for importing it into EGL with `EGL_EXT_image_dma_buf_import`
+    ///
+    /// Every plane is exported as separate file descriptor with [`Self::fd_for_plane()`]
+    /// and described by its file descriptor, offset and pitch. Unless the buffer object
+    /// uses an implicit layout (`Modifier::Invalid`), the modifier is included for every
+    /// plane, which requires `EGL_EXT_image_dma_buf_import_modifiers`.
+    pub fn egl_dma_buf_attributes(&self) -> IoResult<EglDmaBufAttributes> {
+        let modifier = self.modifier();
+        let mut attributes = vec![
+            (EGL_WIDTH, self.width() as EGLint),
+            (EGL_HEIGHT, self.height() as EGLint),
+            (EGL_LINUX_DRM_FOURCC_EXT, self.format() as u32 as EGLint),
+        ];
+        let mut fds = Vec::new();
+        for (plane, keys) in self.planes().zip(PLANE_ATTRIBUTES) {
+            let fd = self.fd_for_plane(plane.index)?;
+            attributes.push((keys[0], fd.as_raw_fd()));
+            attributes.push((keys[1], plane.offset as EGLint));
+            attributes.push((keys[2], plane.stride as EGLint));
+            if modifier != Modifier::Invalid {
+                let modifier = u64::from(modifier);
+                attributes.push((keys[3], modifier as u32 as EGLint));
+                attributes.push((keys[4], (modifier >> 32) as u32 as EGLint));
+            }
+            fds.push(fd);
+        }
+        Ok(EglDmaBufAttributes {
+            attributes,
+            _fds: fds,
+        })
+    }
+}
@@ -0,0 +1,130 @@
+use crate::InvalidFdError;
+
+use std::mem::MaybeUninit;
+use std::os::unix::io::{AsRawFd, OwnedFd, RawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static TRACKER: Mutex<Option<FdExportTracker>> = Mutex::new(None);
+// Whether `TRACKER` is set, so exports without a limit don't contend on the lock
+static LIMIT_SET: AtomicBool = AtomicBool::new(false);
+
+// File descriptors exported while a limit is set, identified by the file they referred to
+// at the time of the export. Closed file descriptors can't be observed directly, so entries
+// are dropped once `fstat` fails or the descriptor number was reused for another file.
+struct FdExportTracker {
+    limit: usize,
+    exported: Vec<(RawFd, libc::dev_t, libc::ino_t)>,
+}
+
+impl FdExportTracker {
+    fn prune(&mut self) {
+        self.exported
+            .retain(|&(fd, dev, ino)| stat(fd) == Some((dev, ino)));
+    }
+}
+
+fn stat(fd: RawFd) -> Option<(libc::dev_t, libc::ino_t)> {
+    let mut stat = MaybeUninit::<libc::stat>::uninit();
+    if unsafe { libc::fstat(fd, stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let stat = unsafe { stat.assume_init() };
+    Some((stat.st_dev, stat.st_ino))
+}
+
+/// Limit the number of open DMA-BUF file descriptors exported by this crate
+///
+/// This is a diagnostic to catch leaked file descriptors during development. While a
+/// limit is set, every file descriptor returned by [`BufferObject::fd()`] and
+/// [`BufferObject::fd_for_plane()`] is tracked until it is closed. Once `limit` of them
+/// are open, further exports fail with [`InvalidFdError`] and log a warning, instead of
+/// exhausting the file descriptor table of the process and failing elsewhere.
+///
+/// Only file descriptors exported after the limit was set are counted. Closing is
+/// detected when the next file descriptor is exported, so a closed descriptor whose
+/// number was reused for another export of the same buffer is still counted.
+///
+/// [`BufferObject::fd()`]: crate::BufferObject::fd()
+/// [`BufferObject::fd_for_plane()`]: crate::BufferObject::fd_for_plane()
+pub fn set_fd_export_limit(limit: usize) {
+    let mut tracker = TRACKER.lock().unwrap();
+    match &mut *tracker {
+        Some(tracker) => tracker.limit = limit,
+        None => {
+            *tracker = Some(FdExportTracker {
+                limit,
+                exported: Vec::new(),
+            })
+        }
+    }
+    LIMIT_SET.store(true, Ordering::Release);
+}
+
+/// Remove the limit set with [`set_fd_export_limit()`] and stop tracking exports
+pub fn clear_fd_export_limit() {
+    let mut tracker = TRACKER.lock().unwrap();
+    *tracker = None;
+    LIMIT_SET.store(false, Ordering::Release);
+}
+
+/// Number of open DMA-BUF file descriptors exported since [`set_fd_export_limit()`]
+///
+/// Always returns 0 if no limit is set.
+pub fn exported_fd_count() -> usize {
+    TRACKER.lock().unwrap().as_mut().map_or(0, |tracker| {
+        tracker.prune();
+        tracker.exported.len()
+    })
+}
+
+// Exports a file descriptor with `export`, unless the limit of open exports is reached
+pub(crate) fn export_fd(
+    export: impl FnOnce() -> Result<OwnedFd, InvalidFdError>,
+) -> Result<OwnedFd, InvalidFdError> {
+    if !LIMIT_SET.load(Ordering::Acquire) {
+        return export();
+    }
+    let mut tracker = TRACKER.lock().unwrap();
+    let Some(tracker) = tracker.as_mut() else {
+        return export();
+    };
+    tracker.prune();
+    if tracker.exported.len() >= tracker.limit {
+        log::warn!(
+            "Refusing to export DMA-BUF, {} exported file descriptors are still open",
+            tracker.exported.len()
+        );
+        return Err(InvalidFdError);
+    }
+    let fd = export()?;
+    if let Some((dev, ino)) = stat(fd.as_raw_fd()) {
+        tracker.exported.push((fd.as_raw_fd(), dev, ino));
+    }
+    Ok(fd)
+}
+
+#[cfg(test)]
+mod test {
+    use super::FdExportTracker;
+    use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+
+    #[test]
+    fn closed_fds_are_pruned() {
+        // A pipe has an inode of its own, unlike shared device nodes other tests open
+        // concurrently, which might reuse the closed file descriptor number
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        let (dev, ino) = super::stat(read.as_raw_fd()).unwrap();
+        let mut tracker = FdExportTracker {
+            limit: 1,
+            exported: vec![(read.as_raw_fd(), dev, ino)],
+        };
+        tracker.prune();
+        assert_eq!(tracker.exported.len(), 1);
+        drop((read, write));
+        tracker.prune();
+        assert!(tracker.exported.is_empty());
+    }
+}
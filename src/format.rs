@@ -0,0 +1,456 @@
+use crate::{Format, Modifier};
+
+// Modifier names as used in the `DRM_FORMAT_MOD_*` and vendor macros of `drm_fourcc.h`,
+// with the vendor as prefix (`I915_FORMAT_MOD_X_TILED` becomes `INTEL_X_TILED`).
+const NAMED_MODIFIERS: &[(&str, Modifier)] = &[
+    ("LINEAR", Modifier::Linear),
+    ("INVALID", Modifier::Invalid),
+    ("INTEL_X_TILED", Modifier::I915_x_tiled),
+    ("INTEL_Y_TILED", Modifier::I915_y_tiled),
+    ("INTEL_Y_TILED_CCS", Modifier::I915_y_tiled_ccs),
+    (
+        "INTEL_Y_TILED_GEN12_RC_CCS",
+        Modifier::I915_y_tiled_gen12_rc_ccs,
+    ),
+    (
+        "INTEL_Y_TILED_GEN12_MC_CCS",
+        Modifier::I915_y_tiled_gen12_mc_ccs,
+    ),
+    ("SAMSUNG_64_32_TILE", Modifier::Samsung_64_32_tile),
+    ("SAMSUNG_16_16_TILE", Modifier::Samsung_16_16_tile),
+    ("QCOM_COMPRESSED", Modifier::Qcom_compressed),
+    ("VIVANTE_TILED", Modifier::Vivante_tiled),
+    ("VIVANTE_SUPER_TILED", Modifier::Vivante_super_tiled),
+    ("VIVANTE_SPLIT_TILED", Modifier::Vivante_split_tiled),
+    (
+        "VIVANTE_SPLIT_SUPER_TILED",
+        Modifier::Vivante_split_super_tiled,
+    ),
+    ("NVIDIA_TEGRA_TILED", Modifier::Nvidia_tegra_tiled),
+    (
+        "NVIDIA_16BX2_BLOCK_ONE_GOB",
+        Modifier::Nvidia_16bx2_block_one_gob,
+    ),
+    (
+        "NVIDIA_16BX2_BLOCK_TWO_GOB",
+        Modifier::Nvidia_16bx2_block_two_gob,
+    ),
+    (
+        "NVIDIA_16BX2_BLOCK_FOUR_GOB",
+        Modifier::Nvidia_16bx2_block_four_gob,
+    ),
+    (
+        "NVIDIA_16BX2_BLOCK_EIGHT_GOB",
+        Modifier::Nvidia_16bx2_block_eight_gob,
+    ),
+    (
+        "NVIDIA_16BX2_BLOCK_SIXTEEN_GOB",
+        Modifier::Nvidia_16bx2_block_sixteen_gob,
+    ),
+    (
+        "NVIDIA_16BX2_BLOCK_THIRTYTWO_GOB",
+        Modifier::Nvidia_16bx2_block_thirtytwo_gob,
+    ),
+    ("BROADCOM_VC4_T_TILED", Modifier::Broadcom_vc4_t_tiled),
+    ("BROADCOM_SAND32", Modifier::Broadcom_sand32),
+    ("BROADCOM_SAND64", Modifier::Broadcom_sand64),
+    ("BROADCOM_SAND128", Modifier::Broadcom_sand128),
+    ("BROADCOM_SAND256", Modifier::Broadcom_sand256),
+    ("BROADCOM_UIF", Modifier::Broadcom_uif),
+    ("ALLWINNER_TILED", Modifier::Allwinner_tiled),
+    ("GENERIC_16_16_TILE", Modifier::Generic_16_16_tile),
+];
+
+/// Parse a modifier from its name or its hexadecimal value
+///
+/// Named modifiers use the vendor as prefix, e.g. `"LINEAR"` or `"INTEL_X_TILED"`,
+/// optionally prefixed with `DRM_FORMAT_MOD_`, and are matched case-insensitively.
+/// Any other modifier can be given as hexadecimal value, e.g. `"0x0100000000000001"`.
+pub fn parse_modifier(s: &str) -> Option<Modifier> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        return u64::from_str_radix(hex, 16).ok().map(Modifier::from);
+    }
+
+    let name = match s.get(..15) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("DRM_FORMAT_MOD_") => &s[15..],
+        _ => s,
+    };
+    NAMED_MODIFIERS
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, modifier)| *modifier)
+}
+
+/// Get a string representation of a modifier that can be parsed by [`parse_modifier()`]
+///
+/// Known modifiers are represented by their name, others by their hexadecimal value.
+pub fn modifier_to_string(modifier: Modifier) -> String {
+    NAMED_MODIFIERS
+        .iter()
+        .find(|(_, m)| *m == modifier)
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| format!("{:#018x}", u64::from(modifier)))
+}
+
+/// Convert a raw fourcc code, e.g. received over a protocol, into a [`Format`]
+///
+/// Returns `None` for codes unknown to [`drm_fourcc`].
+pub fn fourcc_to_format(fourcc: u32) -> Option<Format> {
+    Format::try_from(fourcc).ok()
+}
+
+/// Check whether a raw fourcc code is a known format
+///
+/// This doesn't require a device, e.g. to reject invalid fourcc codes received from
+/// untrusted clients early. Use [`Device::is_format_supported()`](crate::Device::is_format_supported())
+/// to check whether a device supports the format.
+pub fn is_known_format(fourcc: u32) -> bool {
+    fourcc_to_format(fourcc).is_some()
+}
+
+/// Convert a [`Format`] into its raw fourcc code
+pub fn format_to_fourcc(format: Format) -> u32 {
+    format as u32
+}
+
+/// Layout of a single plane of a planar format
+///
+/// See [`planar_info()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlaneFormatInfo {
+    /// Format of the components stored in the plane, e.g. [`Format::R8`] for the luma
+    /// plane and [`Format::Gr88`] for the interleaved chroma plane of [`Format::Nv12`]
+    pub format: Format,
+    /// Horizontal subsampling of the plane relative to the image width
+    pub horizontal_subsampling: u32,
+    /// Vertical subsampling of the plane relative to the image height
+    pub vertical_subsampling: u32,
+    /// Number of bytes of one sample of the plane, i.e. of a block of pixels of the size
+    /// of the subsampling
+    pub bytes_per_block: u32,
+}
+
+impl PlaneFormatInfo {
+    const fn new(format: Format, horizontal: u32, vertical: u32, bytes: u32) -> Self {
+        PlaneFormatInfo {
+            format,
+            horizontal_subsampling: horizontal,
+            vertical_subsampling: vertical,
+            bytes_per_block: bytes,
+        }
+    }
+}
+
+/// Layout of the planes of a planar YUV format
+///
+/// See [`planar_info()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlanarFormatInfo {
+    /// Layout of every plane in order
+    pub planes: &'static [PlaneFormatInfo],
+}
+
+const NV_420: &[PlaneFormatInfo] = &[
+    PlaneFormatInfo::new(Format::R8, 1, 1, 1),
+    PlaneFormatInfo::new(Format::Gr88, 2, 2, 2),
+];
+const NV_420_SWAPPED: &[PlaneFormatInfo] = &[
+    PlaneFormatInfo::new(Format::R8, 1, 1, 1),
+    PlaneFormatInfo::new(Format::Rg88, 2, 2, 2),
+];
+const NV_422: &[PlaneFormatInfo] = &[
+    PlaneFormatInfo::new(Format::R8, 1, 1, 1),
+    PlaneFormatInfo::new(Format::Gr88, 2, 1, 2),
+];
+const NV_422_SWAPPED: &[PlaneFormatInfo] = &[
+    PlaneFormatInfo::new(Format::R8, 1, 1, 1),
+    PlaneFormatInfo::new(Format::Rg88, 2, 1, 2),
+];
+const NV_444: &[PlaneFormatInfo] = &[
+    PlaneFormatInfo::new(Format::R8, 1, 1, 1),
+    PlaneFormatInfo::new(Format::Gr88, 1, 1, 2),
+];
+const NV_444_SWAPPED: &[PlaneFormatInfo] = &[
+    PlaneFormatInfo::new(Format::R8, 1, 1, 1),
+    PlaneFormatInfo::new(Format::Rg88, 1, 1, 2),
+];
+const P_420: &[PlaneFormatInfo] = &[
+    PlaneFormatInfo::new(Format::R16, 1, 1, 2),
+    PlaneFormatInfo::new(Format::Gr1616, 2, 2, 4),
+];
+const P_422: &[PlaneFormatInfo] = &[
+    PlaneFormatInfo::new(Format::R16, 1, 1, 2),
+    PlaneFormatInfo::new(Format::Gr1616, 2, 1, 4),
+];
+
+const fn yuv_planes(horizontal: u32, vertical: u32) -> [PlaneFormatInfo; 3] {
+    [
+        PlaneFormatInfo::new(Format::R8, 1, 1, 1),
+        PlaneFormatInfo::new(Format::R8, horizontal, vertical, 1),
+        PlaneFormatInfo::new(Format::R8, horizontal, vertical, 1),
+    ]
+}
+const YUV_410: &[PlaneFormatInfo] = &yuv_planes(4, 4);
+const YUV_411: &[PlaneFormatInfo] = &yuv_planes(4, 1);
+const YUV_420: &[PlaneFormatInfo] = &yuv_planes(2, 2);
+const YUV_422: &[PlaneFormatInfo] = &yuv_planes(2, 1);
+const YUV_444: &[PlaneFormatInfo] = &yuv_planes(1, 1);
+
+/// Get the layout of the planes of a planar YUV format
+///
+/// Describes the component format and subsampling of every plane, e.g. for
+/// [`Format::Nv12`] a full resolution [`Format::R8`] luma plane followed by an
+/// interleaved [`Format::Gr88`] chroma plane subsampled by two in both directions.
+/// The chroma planes of formats with swapped chroma order, e.g. [`Format::Nv21`] or
+/// [`Format::Yvu420`], are described in memory order.
+///
+/// Returns `None` for formats with a single plane, e.g. RGB or packed YUV formats.
+pub fn planar_info(format: Format) -> Option<PlanarFormatInfo> {
+    let planes = match format {
+        Format::Nv12 => NV_420,
+        Format::Nv21 => NV_420_SWAPPED,
+        Format::Nv16 => NV_422,
+        Format::Nv61 => NV_422_SWAPPED,
+        Format::Nv24 => NV_444,
+        Format::Nv42 => NV_444_SWAPPED,
+        Format::P010 | Format::P012 | Format::P016 => P_420,
+        Format::P210 => P_422,
+        Format::Yuv410 | Format::Yvu410 => YUV_410,
+        Format::Yuv411 | Format::Yvu411 => YUV_411,
+        Format::Yuv420 | Format::Yvu420 => YUV_420,
+        Format::Yuv422 | Format::Yvu422 => YUV_422,
+        Format::Yuv444 | Format::Yvu444 => YUV_444,
+        _ => return None,
+    };
+    Some(PlanarFormatInfo { planes })
+}
+
+// Bit offsets of red, green, blue and alpha of formats with 10-bit color channels,
+// `None` for alpha of formats with a padding channel
+fn rgb10_shifts(format: Format) -> Option<(u32, u32, u32, Option<u32>)> {
+    match format {
+        Format::Argb2101010 => Some((20, 10, 0, Some(30))),
+        Format::Xrgb2101010 => Some((20, 10, 0, None)),
+        Format::Abgr2101010 => Some((0, 10, 20, Some(30))),
+        Format::Xbgr2101010 => Some((0, 10, 20, None)),
+        Format::Rgba1010102 => Some((22, 12, 2, Some(0))),
+        Format::Rgbx1010102 => Some((22, 12, 2, None)),
+        Format::Bgra1010102 => Some((2, 12, 22, Some(0))),
+        Format::Bgrx1010102 => Some((2, 12, 22, None)),
+        _ => None,
+    }
+}
+
+/// Pack a pixel into a format with 10-bit color channels, e.g. [`Format::Argb2101010`]
+///
+/// `rgba` holds red, green and blue in the range `0..=1023` and alpha in the range
+/// `0..=3`, higher bits are discarded. Alpha is ignored for formats with a padding
+/// channel, e.g. [`Format::Xrgb2101010`]. The result is stored as little-endian word.
+///
+/// Returns `None` for other formats.
+pub fn pack_rgb10(format: Format, rgba: [u16; 4]) -> Option<u32> {
+    let (r, g, b, a) = rgb10_shifts(format)?;
+    let [red, green, blue, alpha] = rgba;
+    let mut pixel = (u32::from(red) & 0x3ff) << r
+        | (u32::from(green) & 0x3ff) << g
+        | (u32::from(blue) & 0x3ff) << b;
+    if let Some(a) = a {
+        pixel |= (u32::from(alpha) & 0x3) << a;
+    }
+    Some(pixel)
+}
+
+/// Unpack a pixel of a format with 10-bit color channels into red, green, blue and alpha
+///
+/// This is the inverse of [`pack_rgb10()`]. Alpha is `3`, i.e. opaque, for formats with
+/// a padding channel.
+///
+/// Returns `None` for other formats.
+pub fn unpack_rgb10(format: Format, pixel: u32) -> Option<[u16; 4]> {
+    let (r, g, b, a) = rgb10_shifts(format)?;
+    let channel = |shift: u32, mask: u32| ((pixel >> shift) & mask) as u16;
+    Some([
+        channel(r, 0x3ff),
+        channel(g, 0x3ff),
+        channel(b, 0x3ff),
+        a.map_or(3, |a| channel(a, 0x3)),
+    ])
+}
+
+/// Order of the color channels of a pixel in memory
+///
+/// DRM formats are defined on little-endian words, so the byte order in memory is
+/// reversed compared to the format name, e.g. [`Format::Argb8888`] is stored as
+/// [`ChannelOrder::Bgra`]. Padding channels (e.g. of [`Format::Xrgb8888`]) take the
+/// place of alpha.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelOrder {
+    /// Red, green, blue, alpha
+    Rgba,
+    /// Blue, green, red, alpha
+    Bgra,
+    /// Alpha, red, green, blue
+    Argb,
+    /// Alpha, blue, green, red
+    Abgr,
+}
+
+impl ChannelOrder {
+    /// Get the channel order of a format with four 8-bit channels
+    ///
+    /// Returns `None` for other formats, e.g. YUV or planar formats.
+    pub fn from_format(format: Format) -> Option<ChannelOrder> {
+        match format {
+            Format::Argb8888 | Format::Xrgb8888 => Some(ChannelOrder::Bgra),
+            Format::Abgr8888 | Format::Xbgr8888 => Some(ChannelOrder::Rgba),
+            Format::Rgba8888 | Format::Rgbx8888 => Some(ChannelOrder::Abgr),
+            Format::Bgra8888 | Format::Bgrx8888 => Some(ChannelOrder::Argb),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        format_to_fourcc, fourcc_to_format, is_known_format, modifier_to_string, pack_rgb10,
+        parse_modifier, planar_info, unpack_rgb10, ChannelOrder,
+    };
+    use crate::{Format, Modifier};
+
+    #[test]
+    fn channel_order() {
+        assert_eq!(
+            ChannelOrder::from_format(Format::Argb8888),
+            Some(ChannelOrder::Bgra)
+        );
+        assert_eq!(
+            ChannelOrder::from_format(Format::Xbgr8888),
+            Some(ChannelOrder::Rgba)
+        );
+        assert_eq!(ChannelOrder::from_format(Format::Nv12), None);
+    }
+
+    #[test]
+    fn nv12_planar_info() {
+        let info = planar_info(Format::Nv12).unwrap();
+        assert_eq!(info.planes.len(), 2);
+        assert_eq!(info.planes[0].format, Format::R8);
+        assert_eq!(info.planes[1].format, Format::Gr88);
+        assert_eq!(info.planes[1].horizontal_subsampling, 2);
+        assert_eq!(info.planes[1].vertical_subsampling, 2);
+        assert_eq!(info.planes[1].bytes_per_block, 2);
+    }
+
+    #[test]
+    fn three_plane_planar_info() {
+        let info = planar_info(Format::Yuv422).unwrap();
+        assert_eq!(info.planes.len(), 3);
+        assert!(info.planes[1..]
+            .iter()
+            .all(|plane| plane.horizontal_subsampling == 2 && plane.vertical_subsampling == 1));
+        assert_eq!(planar_info(Format::Argb8888), None);
+        assert_eq!(planar_info(Format::Yuyv), None);
+    }
+
+    #[test]
+    fn pack_rgb10_layout() {
+        assert_eq!(
+            pack_rgb10(Format::Argb2101010, [0x3ff, 0, 0, 3]),
+            Some(0xfff0_0000)
+        );
+        assert_eq!(
+            pack_rgb10(Format::Abgr2101010, [0x3ff, 0, 0, 0]),
+            Some(0x0000_03ff)
+        );
+        assert_eq!(
+            pack_rgb10(Format::Rgba1010102, [0, 0, 0x3ff, 3]),
+            Some(0x0000_0fff)
+        );
+        assert_eq!(
+            pack_rgb10(Format::Xrgb2101010, [0, 0x3ff, 0, 3]),
+            Some(0x000f_fc00)
+        );
+        assert_eq!(pack_rgb10(Format::Argb8888, [0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn unpack_rgb10_roundtrip() {
+        for format in [
+            Format::Argb2101010,
+            Format::Abgr2101010,
+            Format::Rgba1010102,
+            Format::Bgra1010102,
+        ] {
+            let rgba = [0x123, 0x3ff, 0x001, 2];
+            let pixel = pack_rgb10(format, rgba).unwrap();
+            assert_eq!(unpack_rgb10(format, pixel), Some(rgba));
+        }
+        assert_eq!(
+            unpack_rgb10(Format::Xbgr2101010, 0xc000_0000),
+            Some([0, 0, 0, 3])
+        );
+        assert_eq!(unpack_rgb10(Format::Nv12, 0), None);
+    }
+
+    #[test]
+    fn fourcc_format_roundtrip() {
+        assert_eq!(format_to_fourcc(Format::Argb8888), 0x34325241);
+        assert_eq!(fourcc_to_format(0x34325241), Some(Format::Argb8888));
+        assert_eq!(fourcc_to_format(0), None);
+        assert!(is_known_format(0x34325241));
+        assert!(!is_known_format(0xdeadbeef));
+    }
+
+    #[test]
+    fn gbm_format_aliases() {
+        assert_eq!(ffi::GBM_FORMAT_XRGB8888, format_to_fourcc(Format::Xrgb8888));
+        assert_eq!(ffi::GBM_FORMAT_NV12, format_to_fourcc(Format::Nv12));
+    }
+
+    #[test]
+    fn parse_named_modifier() {
+        assert_eq!(parse_modifier("LINEAR"), Some(Modifier::Linear));
+        assert_eq!(
+            parse_modifier("intel_x_tiled"),
+            Some(Modifier::I915_x_tiled)
+        );
+        assert_eq!(
+            parse_modifier("DRM_FORMAT_MOD_INVALID"),
+            Some(Modifier::Invalid)
+        );
+        assert_eq!(parse_modifier("NOT_A_MODIFIER"), None);
+    }
+
+    #[test]
+    fn parse_hex_modifier() {
+        assert_eq!(
+            parse_modifier("0x0100000000000001"),
+            Some(Modifier::I915_x_tiled)
+        );
+        assert_eq!(
+            parse_modifier("0x0300000000001234"),
+            Some(Modifier::Unrecognized(0x0300000000001234))
+        );
+        assert_eq!(parse_modifier("0xzz"), None);
+    }
+
+    #[test]
+    fn modifier_string_roundtrip() {
+        for modifier in [
+            Modifier::Linear,
+            Modifier::I915_y_tiled_ccs,
+            Modifier::Unrecognized(0x0300000000001234),
+        ] {
+            assert_eq!(
+                parse_modifier(&modifier_to_string(modifier)),
+                Some(modifier)
+            );
+        }
+        assert_eq!(
+            modifier_to_string(Modifier::Unrecognized(0x0300000000001234)),
+            "0x0300000000001234"
+        );
+    }
+}
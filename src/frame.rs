@@ -0,0 +1,217 @@
+use crate::{
+    BufferObject, BufferObjectFlags, Device, DmabufImportBuilder, Format, ImportSource, Modifier,
+};
+
+use std::error;
+use std::fmt;
+use std::io::{Error as IoError, Result as IoResult};
+use std::os::unix::io::{AsFd, OwnedFd};
+
+/// Parameters of a named buffer object allocated by [`Device::create_frame_resources()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferSpec<'a> {
+    /// Name to look up the buffer object in the [`FrameResources`]
+    pub name: &'a str,
+    /// Width of the buffer object
+    pub width: u32,
+    /// Height of the buffer object
+    pub height: u32,
+    /// Format of the buffer object
+    pub format: Format,
+    /// Usage flags of the buffer object
+    pub usage: BufferObjectFlags,
+}
+
+/// A group of named buffer objects, e.g. the buffers of one frame of a video pipeline
+///
+/// Created by [`Device::create_frame_resources()`].
+#[derive(Debug)]
+pub struct FrameResources<U: 'static> {
+    buffers: Vec<(String, BufferObject<U>)>,
+}
+
+impl<U: 'static> FrameResources<U> {
+    /// Get the buffer object with the given name
+    pub fn get(&self, name: &str) -> Option<&BufferObject<U>> {
+        self.buffers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, bo)| bo)
+    }
+
+    /// Get the buffer object with the given name mutably
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut BufferObject<U>> {
+        self.buffers
+            .iter_mut()
+            .find(|(n, _)| n == name)
+            .map(|(_, bo)| bo)
+    }
+
+    /// Iterate over the names and buffer objects in allocation order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &BufferObject<U>)> {
+        self.buffers.iter().map(|(name, bo)| (name.as_str(), bo))
+    }
+
+    /// Number of buffer objects in the group
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Whether the group contains no buffer objects
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+
+    /// Take the buffer objects out of the group
+    pub fn into_buffers(self) -> Vec<(String, BufferObject<U>)> {
+        self.buffers
+    }
+}
+
+/// Allocating a buffer object of [`FrameResources`] failed
+#[derive(Debug)]
+pub struct FrameAllocationError {
+    /// Name of the buffer object, that failed to allocate
+    pub name: String,
+    /// The error of the failed allocation
+    pub source: IoError,
+}
+
+impl fmt::Display for FrameAllocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Failed to allocate buffer \"{}\": {}",
+            self.name, self.source
+        )
+    }
+}
+
+impl error::Error for FrameAllocationError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl<T: AsFd> Device<T> {
+    /// Allocate a group of differently sized and formatted buffer objects
+    ///
+    /// The buffer objects are allocated in the order of `specs` with
+    /// [`Self::create_buffer_object()`]. If any allocation fails, the already allocated
+    /// buffer objects are freed again and an error of the same kind as the failed
+    /// allocation is returned, wrapping a [`FrameAllocationError`] naming the buffer.
+    pub fn create_frame_resources<U: 'static>(
+        &self,
+        specs: &[BufferSpec<'_>],
+    ) -> IoResult<FrameResources<U>> {
+        let mut buffers = Vec::with_capacity(specs.len());
+        for spec in specs {
+            match self.create_buffer_object(spec.width, spec.height, spec.format, spec.usage) {
+                Ok(bo) => buffers.push((spec.name.to_owned(), bo)),
+                Err(source) => {
+                    return Err(IoError::new(
+                        source.kind(),
+                        FrameAllocationError {
+                            name: spec.name.to_owned(),
+                            source,
+                        },
+                    ))
+                }
+            }
+        }
+        Ok(FrameResources { buffers })
+    }
+}
+
+/// An owned snapshot of the layout and memory of a [`BufferObject`]
+///
+/// Unlike the buffer object itself, a `DmaBufFrame` holds its own file descriptors of the
+/// planes and doesn't borrow or keep alive the buffer object it was exported from, so it
+/// can be sent to another thread, e.g. of a video encoder. Use [`Self::import_source()`]
+/// to import it as buffer object again:
+///
+/// ```rust,no_run
+/// # use gbm::{BufferObject, BufferObjectFlags, Device, DmaBufFrame};
+/// # fn export(gbm: &Device<std::fs::File>, bo: &BufferObject<()>) -> std::io::Result<()> {
+/// let frame = DmaBufFrame::from_bo(bo)?;
+/// let reimported = gbm.import::<()>(frame.import_source()?, BufferObjectFlags::RENDERING)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DmaBufFrame {
+    /// Width of the buffer
+    pub width: u32,
+    /// Height of the buffer
+    pub height: u32,
+    /// Format of the buffer
+    pub format: Format,
+    /// Modifier of the buffer
+    pub modifier: Modifier,
+    /// Layout and memory of every plane
+    pub planes: Vec<DmaBufFramePlane>,
+}
+
+/// Layout and memory of a single plane of a [`DmaBufFrame`]
+#[derive(Debug)]
+pub struct DmaBufFramePlane {
+    /// Exported dma buffer of the plane
+    pub fd: OwnedFd,
+    /// Offset of the plane into the dma buffer
+    pub offset: u32,
+    /// Stride of the plane
+    pub stride: u32,
+}
+
+impl DmaBufFrame {
+    /// Export the layout and planes of a buffer object
+    ///
+    /// Every plane is exported as separate file descriptor with
+    /// [`BufferObject::fd_for_plane()`].
+    pub fn from_bo<T: 'static>(bo: &BufferObject<T>) -> IoResult<DmaBufFrame> {
+        let planes = bo
+            .planes()
+            .map(|plane| {
+                Ok(DmaBufFramePlane {
+                    fd: bo.fd_for_plane(plane.index)?,
+                    offset: plane.offset,
+                    stride: plane.stride,
+                })
+            })
+            .collect::<IoResult<Vec<_>>>()?;
+
+        Ok(DmaBufFrame {
+            width: bo.width(),
+            height: bo.height(),
+            format: bo.format(),
+            modifier: bo.modifier(),
+            planes,
+        })
+    }
+
+    /// Describe the frame for importing it with [`Device::import()`]
+    ///
+    /// Fails with [`std::io::ErrorKind::InvalidInput`] under the same conditions as
+    /// [`DmabufImportBuilder::build()`], e.g. for frames without planes.
+    pub fn import_source(&self) -> IoResult<ImportSource<'_>> {
+        self.planes
+            .iter()
+            .fold(
+                DmabufImportBuilder::new()
+                    .dimensions(self.width, self.height)
+                    .format(self.format)
+                    .modifier(self.modifier),
+                |builder, plane| builder.plane(plane.fd.as_fd(), plane.offset, plane.stride),
+            )
+            .build()
+    }
+}
+
+impl<T: 'static> TryFrom<&BufferObject<T>> for DmaBufFrame {
+    type Error = IoError;
+
+    /// See [`DmaBufFrame::from_bo()`]
+    fn try_from(bo: &BufferObject<T>) -> IoResult<DmaBufFrame> {
+        DmaBufFrame::from_bo(bo)
+    }
+}
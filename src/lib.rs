@@ -105,11 +105,41 @@ extern crate bitflags;
 
 mod buffer_object;
 mod device;
+#[cfg(feature = "dma-buf-sync")]
+mod dma_buf;
+#[cfg(feature = "import-egl")]
+mod egl;
+mod fd_limit;
+mod format;
+mod frame;
+mod local;
+#[cfg(feature = "test-mock")]
+mod mock;
+#[cfg(feature = "serde")]
+pub mod serialize;
 mod surface;
+mod swapchain;
+mod symbols;
+#[cfg(feature = "wgpu")]
+mod wgpu;
 
 pub use self::buffer_object::*;
 pub use self::device::*;
+#[cfg(feature = "dma-buf-sync")]
+pub use self::dma_buf::DmaBufSyncAccess;
+#[cfg(feature = "import-egl")]
+pub use self::egl::*;
+pub use self::fd_limit::*;
+pub use self::format::*;
+pub use self::frame::*;
+pub use self::local::*;
+#[cfg(feature = "test-mock")]
+pub use self::mock::*;
 pub use self::surface::*;
+pub use self::swapchain::*;
+pub use self::symbols::library_version;
+#[cfg(feature = "wgpu")]
+pub use self::wgpu::*;
 pub use drm_fourcc::{DrmFourcc as Format, DrmModifier as Modifier};
 
 use std::fmt;
@@ -146,6 +176,16 @@ impl<T> Ptr<T> {
     fn new<F: FnOnce(*mut T) + Send + 'static>(ptr: *mut T, destructor: F) -> Ptr<T> {
         Ptr(Arc::new(PtrDrop(ptr, Some(Box::new(destructor)))))
     }
+
+    // Number of references to the pointer, including this one
+    fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+
+    // Run the destructor now, fails returning the pointer if other references exist
+    fn try_drop(self) -> Result<(), Ptr<T>> {
+        Arc::try_unwrap(self.0).map(drop).map_err(Ptr)
+    }
 }
 
 impl<T> std::ops::Deref for Ptr<T> {
@@ -181,6 +221,14 @@ mod test {
         is_sync::<super::Device<OwnedFd>>();
     }
 
+    #[test]
+    fn shared_device_is_clone_send_sync() {
+        fn is_clone<T: Clone>() {}
+        is_clone::<super::SharedDevice<OwnedFd>>();
+        is_send::<super::SharedDevice<OwnedFd>>();
+        is_sync::<super::SharedDevice<OwnedFd>>();
+    }
+
     #[test]
     fn surface_is_send() {
         is_send::<super::Surface<std::fs::File>>();
@@ -193,6 +241,11 @@ mod test {
         is_sync::<super::Surface<OwnedFd>>();
     }
 
+    #[test]
+    fn dma_buf_frame_is_send() {
+        is_send::<super::DmaBufFrame>();
+    }
+
     #[test]
     fn unmapped_bo_is_send() {
         is_send::<super::BufferObject<()>>();
@@ -0,0 +1,190 @@
+use crate::{
+    BufferObject, BufferObjectFlags, Device, Format, FrontBufferError, ImportSource, Modifier,
+    Surface,
+};
+
+use std::fmt;
+use std::io::Result as IoResult;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::os::unix::io::AsFd;
+
+// Marker making the containing type neither `Send` nor `Sync`
+type NotSend = PhantomData<*const ()>;
+
+/// A [`Device`] confined to the thread it was created on
+///
+/// [`Device`] and the objects created from it are `Send` and `Sync`, which is sound for
+/// Mesa's backend. libgbm doesn't specify any threading requirements for its backends
+/// though, so backends loaded through `GBM_BACKEND`, e.g. ones wrapping driver APIs with
+/// thread-local state, might require all calls to happen on the thread that created the
+/// device. Using such a backend from multiple threads may silently corrupt its state.
+///
+/// A `LocalDevice` is neither `Send` nor `Sync` and the buffer objects and surfaces it
+/// creates are wrapped in [`LocalBufferObject`] and [`LocalSurface`], which aren't either,
+/// so the compiler rejects moving any of them to another thread. Objects obtained by
+/// calling methods of the wrapped types directly, e.g. [`BufferObject::dup()`] or
+/// [`Surface::try_acquire()`], aren't confined.
+#[must_use = "dropping this immediately destroys the GBM device"]
+pub struct LocalDevice<T: AsFd> {
+    device: Device<T>,
+    _not_send: NotSend,
+}
+
+impl<T: AsFd> fmt::Debug for LocalDevice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("LocalDevice").field(&self.device).finish()
+    }
+}
+
+impl<T: AsFd> LocalDevice<T> {
+    /// Open a GBM device confined to the current thread, see [`Device::new()`]
+    pub fn new(fd: T) -> IoResult<LocalDevice<T>> {
+        Device::new(fd).map(LocalDevice::from_device)
+    }
+
+    /// Confine an already opened device to the current thread
+    pub fn from_device(device: Device<T>) -> LocalDevice<T> {
+        LocalDevice {
+            device,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Test if a format is supported for a given set of usage flags,
+    /// see [`Device::is_format_supported()`]
+    pub fn is_format_supported(&self, format: Format, usage: BufferObjectFlags) -> bool {
+        self.device.is_format_supported(format, usage)
+    }
+
+    /// Allocate a buffer object, see [`Device::create_buffer_object()`]
+    pub fn create_buffer_object<U: 'static>(
+        &self,
+        width: u32,
+        height: u32,
+        format: Format,
+        usage: BufferObjectFlags,
+    ) -> IoResult<LocalBufferObject<U>> {
+        self.device
+            .create_buffer_object(width, height, format, usage)
+            .map(LocalBufferObject::new)
+    }
+
+    /// Allocate a buffer object with explicit modifiers and flags,
+    /// see [`Device::create_buffer_object_with_modifiers2()`]
+    pub fn create_buffer_object_with_modifiers2<U: 'static>(
+        &self,
+        width: u32,
+        height: u32,
+        format: Format,
+        modifiers: impl Iterator<Item = Modifier>,
+        usage: BufferObjectFlags,
+    ) -> IoResult<LocalBufferObject<U>> {
+        self.device
+            .create_buffer_object_with_modifiers2(width, height, format, modifiers, usage)
+            .map(LocalBufferObject::new)
+    }
+
+    /// Import a foreign buffer, see [`Device::import()`]
+    pub fn import<U: 'static>(
+        &self,
+        source: ImportSource<'_>,
+        usage: BufferObjectFlags,
+    ) -> IoResult<LocalBufferObject<U>> {
+        self.device
+            .import(source, usage)
+            .map(LocalBufferObject::new)
+    }
+
+    /// Allocate a surface, see [`Device::create_surface()`]
+    pub fn create_surface<U: 'static>(
+        &self,
+        width: u32,
+        height: u32,
+        format: Format,
+        usage: BufferObjectFlags,
+    ) -> IoResult<LocalSurface<U>> {
+        self.device
+            .create_surface(width, height, format, usage)
+            .map(|surface| LocalSurface {
+                surface,
+                _not_send: PhantomData,
+            })
+    }
+}
+
+/// A [`BufferObject`] created by a [`LocalDevice`], confined to its thread
+///
+/// Dereferences to the wrapped [`BufferObject`].
+#[must_use = "dropping this immediately frees the GPU buffer"]
+pub struct LocalBufferObject<U: 'static> {
+    bo: BufferObject<U>,
+    _not_send: NotSend,
+}
+
+impl<U: 'static> LocalBufferObject<U> {
+    fn new(bo: BufferObject<U>) -> LocalBufferObject<U> {
+        LocalBufferObject {
+            bo,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl<U: 'static> fmt::Debug for LocalBufferObject<U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("LocalBufferObject").field(&self.bo).finish()
+    }
+}
+
+impl<U: 'static> Deref for LocalBufferObject<U> {
+    type Target = BufferObject<U>;
+    fn deref(&self) -> &BufferObject<U> {
+        &self.bo
+    }
+}
+
+impl<U: 'static> DerefMut for LocalBufferObject<U> {
+    fn deref_mut(&mut self) -> &mut BufferObject<U> {
+        &mut self.bo
+    }
+}
+
+/// A [`Surface`] created by a [`LocalDevice`], confined to its thread
+///
+/// Dereferences to the wrapped [`Surface`], while locking front buffers returns
+/// [`LocalBufferObject`]s.
+#[must_use = "dropping this immediately frees the surface and its buffers"]
+pub struct LocalSurface<U: 'static> {
+    surface: Surface<U>,
+    _not_send: NotSend,
+}
+
+impl<U: 'static> fmt::Debug for LocalSurface<U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("LocalSurface").field(&self.surface).finish()
+    }
+}
+
+impl<U: 'static> LocalSurface<U> {
+    /// Lock the surface's current front buffer, see [`Surface::lock_front_buffer()`]
+    ///
+    /// # Safety
+    /// Same as for [`Surface::lock_front_buffer()`].
+    pub unsafe fn lock_front_buffer(&self) -> Result<LocalBufferObject<U>, FrontBufferError> {
+        self.surface.lock_front_buffer().map(LocalBufferObject::new)
+    }
+}
+
+impl<U: 'static> Deref for LocalSurface<U> {
+    type Target = Surface<U>;
+    fn deref(&self) -> &Surface<U> {
+        &self.surface
+    }
+}
+
+impl<U: 'static> DerefMut for LocalSurface<U> {
+    fn deref_mut(&mut self) -> &mut Surface<U> {
+        &mut self.surface
+    }
+}
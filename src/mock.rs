@@ -0,0 +1,462 @@
+use crate::{BufferObjectFlags, Format, MapError, Modifier};
+
+use std::fmt;
+use std::io::{self, Error as IoError, ErrorKind, Result as IoResult};
+
+/// A device allocating buffer objects in host memory, for tests without a GPU
+///
+/// The [`MockBufferObject`]s created by this device provide the cpu access methods of
+/// [`BufferObject`](crate::BufferObject) with the same signatures, so logic built on top
+/// of them, e.g. blits, format conversions or readback, can be tested deterministically.
+/// Only single plane formats with a known number of bits per pixel are supported and
+/// buffers always use a linear layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MockDevice {
+    stride_alignment: u32,
+}
+
+impl Default for MockDevice {
+    fn default() -> MockDevice {
+        MockDevice::new()
+    }
+}
+
+impl MockDevice {
+    /// Create a mock device allocating buffers without padding between rows
+    pub fn new() -> MockDevice {
+        MockDevice::with_stride_alignment(1)
+    }
+
+    /// Create a mock device aligning the stride of every buffer to `alignment` bytes
+    ///
+    /// E.g. `256` mimics drivers padding rows, to test handling of the stride. Values
+    /// smaller than `1` are treated as `1`.
+    pub fn with_stride_alignment(alignment: u32) -> MockDevice {
+        MockDevice {
+            stride_alignment: alignment.max(1),
+        }
+    }
+
+    /// Test if a format is supported by the mock device
+    pub fn is_format_supported(&self, format: Format, _usage: BufferObjectFlags) -> bool {
+        bpp(format).is_some()
+    }
+
+    /// Allocate a zeroed buffer object in host memory
+    ///
+    /// Fails with [`ErrorKind::Unsupported`] for formats not supported by
+    /// [`Self::is_format_supported()`] and with [`ErrorKind::InvalidInput`] for
+    /// empty or too large buffers.
+    pub fn create_buffer_object<U: 'static>(
+        &self,
+        width: u32,
+        height: u32,
+        format: Format,
+        usage: BufferObjectFlags,
+    ) -> IoResult<MockBufferObject<U>> {
+        let bpp = bpp(format).ok_or_else(|| {
+            IoError::new(
+                ErrorKind::Unsupported,
+                format!("{:?} is not supported by the mock device", format),
+            )
+        })?;
+        let invalid = || IoError::new(ErrorKind::InvalidInput, "invalid buffer dimensions");
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+        let row_bytes = (width.checked_mul(bpp).ok_or_else(invalid)? + 7) / 8;
+        let alignment = self.stride_alignment;
+        let stride =
+            row_bytes.checked_add(alignment - 1).ok_or_else(invalid)? / alignment * alignment;
+        let size = (stride as usize)
+            .checked_mul(height as usize)
+            .ok_or_else(invalid)?;
+        Ok(MockBufferObject {
+            data: vec![0; size],
+            width,
+            height,
+            stride,
+            format,
+            bpp,
+            usage,
+            userdata: None,
+        })
+    }
+}
+
+/// A buffer object in host memory, created by a [`MockDevice`]
+pub struct MockBufferObject<T: 'static> {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: Format,
+    bpp: u32,
+    usage: BufferObjectFlags,
+    userdata: Option<T>,
+}
+
+impl<T: 'static> fmt::Debug for MockBufferObject<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MockBufferObject")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("stride", &self.stride)
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl<T: 'static> MockBufferObject<T> {
+    /// Get the width of the buffer object
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get the height of the buffer object
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the stride of the buffer object
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// Get the format of the buffer object
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Get the bits per pixel of the buffer object
+    pub fn bpp(&self) -> u32 {
+        self.bpp
+    }
+
+    /// Get the number of planes, always `1`
+    pub fn plane_count(&self) -> u32 {
+        1
+    }
+
+    /// Get the modifier, always `Modifier::Linear`
+    pub fn modifier(&self) -> Modifier {
+        Modifier::Linear
+    }
+
+    /// The usage flags the buffer object was created with
+    pub fn usage(&self) -> Option<BufferObjectFlags> {
+        Some(self.usage)
+    }
+
+    /// Map a region of the buffer object for cpu read access,
+    /// see [`BufferObject::map()`](crate::BufferObject::map())
+    pub fn map<'a, F, S>(
+        &'a self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        f: F,
+    ) -> Result<S, MapError>
+    where
+        F: FnOnce(&MockMappedBufferObject<'a>) -> S,
+    {
+        let range = self.region(x, y, width, height)?;
+        let mapping = MockMappedBufferObject {
+            buffer: MockMapping::Ref(&self.data[range]),
+            stride: self.stride,
+            width,
+            height,
+            x,
+            y,
+        };
+        Ok(f(&mapping))
+    }
+
+    /// Map a region of the buffer object for cpu read/write access,
+    /// see [`BufferObject::map_mut()`](crate::BufferObject::map_mut())
+    pub fn map_mut<'a, F, S>(
+        &'a mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        f: F,
+    ) -> Result<S, MapError>
+    where
+        F: FnOnce(&mut MockMappedBufferObject<'a>) -> S,
+    {
+        let range = self.region(x, y, width, height)?;
+        let stride = self.stride;
+        let mut mapping = MockMappedBufferObject {
+            buffer: MockMapping::Mut(&mut self.data[range]),
+            stride,
+            width,
+            height,
+            x,
+            y,
+        };
+        Ok(f(&mut mapping))
+    }
+
+    /// Write data into the buffer object, see [`BufferObject::write()`](crate::BufferObject::write())
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] if `buffer` is larger than the buffer object.
+    pub fn write(&mut self, buffer: &[u8]) -> IoResult<()> {
+        match self.data.get_mut(..buffer.len()) {
+            Some(data) => {
+                data.copy_from_slice(buffer);
+                Ok(())
+            }
+            None => Err(IoError::new(
+                ErrorKind::InvalidInput,
+                "data is larger than the buffer object",
+            )),
+        }
+    }
+
+    /// Fill the buffer object with tightly packed pixel data read from `reader`,
+    /// see [`BufferObject::write_from()`](crate::BufferObject::write_from())
+    pub fn write_from(&mut self, reader: &mut impl io::Read) -> IoResult<usize> {
+        let (row_bytes, stride) = (self.row_bytes(), self.stride as usize);
+        for row in self.data.chunks_exact_mut(stride) {
+            reader.read_exact(&mut row[..row_bytes])?;
+        }
+        Ok(row_bytes * self.height as usize)
+    }
+
+    /// Read the pixel data of the buffer object tightly packed into a vector,
+    /// see [`BufferObject::read_to_vec()`](crate::BufferObject::read_to_vec())
+    pub fn read_to_vec(&self) -> IoResult<Vec<u8>> {
+        let (row_bytes, stride) = (self.row_bytes(), self.stride as usize);
+        Ok(self
+            .data
+            .chunks_exact(stride)
+            .flat_map(|row| &row[..row_bytes])
+            .copied()
+            .collect())
+    }
+
+    /// Sets the userdata of the buffer object, returning the previous userdata
+    pub fn set_userdata(&mut self, userdata: T) -> Option<T> {
+        self.userdata.replace(userdata)
+    }
+
+    /// Clears the set userdata of the buffer object
+    pub fn clear_userdata(&mut self) {
+        self.userdata = None;
+    }
+
+    /// Returns a reference to set userdata, if any
+    pub fn userdata(&self) -> Option<&T> {
+        self.userdata.as_ref()
+    }
+
+    /// Returns a mutable reference to set userdata, if any
+    pub fn userdata_mut(&mut self) -> Option<&mut T> {
+        self.userdata.as_mut()
+    }
+
+    /// Takes ownership of the userdata, if any
+    pub fn take_userdata(&mut self) -> Option<T> {
+        self.userdata.take()
+    }
+
+    fn row_bytes(&self) -> usize {
+        ((self.width as usize * self.bpp as usize + 7) / 8).min(self.stride as usize)
+    }
+
+    // Range of the data of a mapped region, which like libgbm's mappings starts at the
+    // top left pixel of the region and spans `height` strides, cut off at the end
+    fn region(
+        &self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<std::ops::Range<usize>, MapError> {
+        if x.checked_add(width)
+            .map_or(true, |right| right > self.width)
+            || y.checked_add(height)
+                .map_or(true, |bottom| bottom > self.height)
+        {
+            return Err(MapError::InvalidRegion);
+        }
+        let start = y as usize * self.stride as usize + x as usize * self.bpp as usize / 8;
+        let end = (start + height as usize * self.stride as usize).min(self.data.len());
+        Ok(start..end)
+    }
+}
+
+enum MockMapping<'a> {
+    Ref(&'a [u8]),
+    Mut(&'a mut [u8]),
+}
+
+/// A mapped region of a [`MockBufferObject`]
+///
+/// Provides the accessors of [`MappedBufferObject`](crate::MappedBufferObject).
+pub struct MockMappedBufferObject<'a> {
+    buffer: MockMapping<'a>,
+    stride: u32,
+    width: u32,
+    height: u32,
+    x: u32,
+    y: u32,
+}
+
+impl<'a> fmt::Debug for MockMappedBufferObject<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MockMappedBufferObject")
+            .field("x", &self.x)
+            .field("y", &self.y)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("stride", &self.stride)
+            .finish()
+    }
+}
+
+impl<'a> MockMappedBufferObject<'a> {
+    /// Get the stride of the mapped region
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// The height of the mapped region
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The width of the mapped region
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The X (top left origin) starting position of the mapped region
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    /// The Y (top left origin) starting position of the mapped region
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    /// Access to the mapped memory
+    pub fn buffer(&self) -> &[u8] {
+        match &self.buffer {
+            MockMapping::Ref(buffer) => buffer,
+            MockMapping::Mut(buffer) => buffer,
+        }
+    }
+
+    /// Mutable access to the mapped memory
+    ///
+    /// # Panics
+    ///
+    /// Panics for regions mapped with [`MockBufferObject::map()`], which are read-only.
+    pub fn buffer_mut(&mut self) -> &mut [u8] {
+        match &mut self.buffer {
+            MockMapping::Ref(_) => panic!("buffer object was mapped for reading only"),
+            MockMapping::Mut(buffer) => buffer,
+        }
+    }
+
+    /// Iterate over the rows of the mapped region as slices of exactly `row_len` bytes,
+    /// see [`MappedBufferObject::rows_exact()`](crate::MappedBufferObject::rows_exact())
+    pub fn rows_exact(&self, row_len: usize) -> impl Iterator<Item = &[u8]> {
+        let stride = self.stride as usize;
+        let rows = if row_len <= stride {
+            self.height as usize
+        } else {
+            0
+        };
+        self.buffer()
+            .chunks(stride.max(1))
+            .take(rows)
+            .filter(move |row| row.len() >= row_len)
+            .map(move |row| &row[..row_len])
+    }
+}
+
+// Bits per pixel of the single plane formats supported by `MockDevice`
+fn bpp(format: Format) -> Option<u32> {
+    Some(match format {
+        Format::C8 | Format::R8 | Format::Rgb332 | Format::Bgr233 => 8,
+        Format::R16
+        | Format::Rg88
+        | Format::Gr88
+        | Format::Rgb565
+        | Format::Bgr565
+        | Format::Xrgb4444
+        | Format::Argb4444
+        | Format::Xbgr4444
+        | Format::Abgr4444
+        | Format::Xrgb1555
+        | Format::Argb1555
+        | Format::Xbgr1555
+        | Format::Abgr1555 => 16,
+        Format::Rgb888 | Format::Bgr888 => 24,
+        Format::Xrgb8888
+        | Format::Argb8888
+        | Format::Xbgr8888
+        | Format::Abgr8888
+        | Format::Rgbx8888
+        | Format::Rgba8888
+        | Format::Bgrx8888
+        | Format::Bgra8888
+        | Format::Xrgb2101010
+        | Format::Argb2101010
+        | Format::Xbgr2101010
+        | Format::Abgr2101010
+        | Format::Rg1616
+        | Format::Gr1616 => 32,
+        Format::Xrgb16161616f
+        | Format::Argb16161616f
+        | Format::Xbgr16161616f
+        | Format::Abgr16161616f => 64,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::MockDevice;
+    use crate::{BufferObjectFlags, Format, MapError};
+
+    #[test]
+    fn mock_buffer_round_trip() {
+        let device = MockDevice::with_stride_alignment(64);
+        let mut bo = device
+            .create_buffer_object::<()>(3, 2, Format::Argb8888, BufferObjectFlags::WRITE)
+            .unwrap();
+        assert_eq!(bo.stride(), 64);
+
+        let data = (0..24).collect::<Vec<u8>>();
+        assert_eq!(bo.write_from(&mut &data[..]).unwrap(), 24);
+        assert_eq!(bo.read_to_vec().unwrap(), data);
+
+        let pixel = bo
+            .map(1, 1, 1, 1, |mapping| mapping.buffer()[..4].to_vec())
+            .unwrap();
+        assert_eq!(pixel, [16, 17, 18, 19]);
+
+        bo.map_mut(0, 0, 3, 2, |mapping| mapping.buffer_mut()[0] = 0xff)
+            .unwrap();
+        assert_eq!(bo.read_to_vec().unwrap()[0], 0xff);
+        assert!(matches!(
+            bo.map(2, 0, 2, 1, |_| ()),
+            Err(MapError::InvalidRegion)
+        ));
+    }
+
+    #[test]
+    fn mock_rejects_planar_formats() {
+        let device = MockDevice::new();
+        assert!(!device.is_format_supported(Format::Nv12, BufferObjectFlags::empty()));
+        assert!(device
+            .create_buffer_object::<()>(4, 4, Format::Nv12, BufferObjectFlags::empty())
+            .is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Human-readable serde representations of [`Format`](crate::Format) and
+//! [`Modifier`](crate::Modifier)
+//!
+//! The serde support of [`drm_fourcc`] serializes formats and modifiers by the names of
+//! their enum variants, which are neither stable nor understood by other tools. These
+//! modules can be used with `#[serde(with = "...")]` instead:
+//!
+//! ```rust,no_run
+//! # use gbm::{Format, Modifier};
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Layout {
+//!     #[serde(with = "gbm::serialize::fourcc")]
+//!     format: Format,
+//!     #[serde(with = "gbm::serialize::modifier")]
+//!     modifier: Modifier,
+//! }
+//! ```
+
+/// Serialize a [`Format`](crate::Format) as its fourcc code, e.g. `"XR24"`
+///
+/// Codes shorter than four characters are padded with spaces on deserialization,
+/// e.g. `"R8"` is read as `"R8  "`.
+pub mod fourcc {
+    use crate::Format;
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::Serializer;
+
+    /// Serialize a format as its fourcc code
+    pub fn serialize<S: Serializer>(format: &Format, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format.to_string())
+    }
+
+    /// Deserialize a format from its fourcc code
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Format, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        from_str(&code).ok_or_else(|| D::Error::custom(format!("unknown fourcc code {:?}", code)))
+    }
+
+    pub(crate) fn from_str(code: &str) -> Option<Format> {
+        if code.len() > 4 || !code.is_ascii() {
+            return None;
+        }
+        let mut bytes = [b' '; 4];
+        bytes[..code.len()].copy_from_slice(code.as_bytes());
+        Format::try_from(u32::from_le_bytes(bytes)).ok()
+    }
+}
+
+/// Serialize a [`Modifier`](crate::Modifier) as its hexadecimal value, e.g.
+/// `"0x0100000000000001"`
+///
+/// Deserialization also accepts the names understood by
+/// [`parse_modifier()`](crate::parse_modifier()), e.g. `"INTEL_X_TILED"`.
+pub mod modifier {
+    use crate::Modifier;
+    use serde::de::{Deserialize, Deserializer, Error};
+    use serde::ser::Serializer;
+
+    /// Serialize a modifier as its hexadecimal value
+    pub fn serialize<S: Serializer>(modifier: &Modifier, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:#018x}", u64::from(*modifier)))
+    }
+
+    /// Deserialize a modifier from its hexadecimal value or name
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Modifier, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        crate::parse_modifier(&value)
+            .ok_or_else(|| D::Error::custom(format!("invalid modifier {:?}", value)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Format, Modifier};
+    use serde::de::value::{BorrowedStrDeserializer, Error};
+
+    fn deserializer(s: &str) -> BorrowedStrDeserializer<'_, Error> {
+        BorrowedStrDeserializer::new(s)
+    }
+
+    #[test]
+    fn fourcc_round_trip() {
+        for format in [Format::Xrgb8888, Format::Nv12, Format::R8] {
+            let code = format.to_string();
+            assert_eq!(super::fourcc::from_str(&code), Some(format));
+        }
+        assert_eq!(super::fourcc::from_str("R8"), Some(Format::R8));
+        assert_eq!(super::fourcc::from_str("XRGB8888"), None);
+        assert!(super::fourcc::deserialize(deserializer("ZZZZ")).is_err());
+    }
+
+    #[test]
+    fn modifier_from_hex_or_name() {
+        assert_eq!(
+            super::modifier::deserialize(deserializer("0x0100000000000001")).unwrap(),
+            Modifier::I915_x_tiled
+        );
+        assert_eq!(
+            super::modifier::deserialize(deserializer("LINEAR")).unwrap(),
+            Modifier::Linear
+        );
+        assert!(super::modifier::deserialize(deserializer("bogus")).is_err());
+    }
+}
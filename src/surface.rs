@@ -1,37 +1,162 @@
-use crate::{AsRaw, BufferObject, Ptr};
+use crate::{
+    AsRaw, BufferObject, BufferObjectFlags, BufferObjectHandle, Device, Format, Modifier, Ptr,
+};
 use std::error;
 use std::fmt;
+use std::io;
 use std::marker::PhantomData;
+use std::os::unix::io::{AsFd, RawFd};
+use std::sync::{Arc, Mutex};
 
 /// A GBM rendering surface
+///
+/// `T` is the type of the userdata attached to the buffer objects returned by
+/// [`Surface::lock_front_buffer()`]. Since libgbm stores userdata on the underlying
+/// `gbm_bo`, which is owned and reused by the surface, userdata set on a front buffer
+/// survives releasing it. When the same underlying buffer is locked again, the previously
+/// attached userdata is returned by [`BufferObject::userdata()`]. This makes it suitable
+/// for per-buffer caches, e.g. of framebuffers. The userdata is dropped once libgbm
+/// destroys the buffer, at the latest when the surface is destroyed.
+#[must_use = "dropping this immediately frees the surface and its buffers"]
 pub struct Surface<T: 'static> {
     // Declare `ffi` first so it is dropped before `_device`
     ffi: Ptr<ffi::gbm_surface>,
     _device: Ptr<ffi::gbm_device>,
+    device_fd: RawFd,
+    locks: Arc<Mutex<LockState>>,
+    width: u32,
+    height: u32,
+    format: Format,
+    // Creation parameters to recreate the surface with, unknown for surfaces wrapped
+    // with `from_raw`
+    creation_params_known: bool,
+    usage: Option<BufferObjectFlags>,
+    modifiers: Option<Box<[Modifier]>>,
+    verify_front_buffer: bool,
     _bo_userdata: PhantomData<T>,
 }
 
+// Bookkeeping of the buffers handed out by `gbm_surface_lock_front_buffer`
+#[derive(Debug, Default)]
+struct LockState {
+    // Addresses of the distinct buffers seen so far, i.e. the learned pool, and the
+    // number of the lock that returned them last
+    seen: Vec<(usize, u64)>,
+    // Number of front buffers locked so far
+    frames: u64,
+    // Number of currently locked buffers
+    locked: usize,
+}
+
 impl<T: 'static> fmt::Debug for Surface<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Surface")
             .field("ptr", &format_args!("{:p}", &self.ffi))
             .field("device", &format_args!("{:p}", &self._device))
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("format", &self.format)
             .finish()
     }
 }
 
+/// A front buffer locked by [`Surface::lock_front_buffer_with_age()`]
+///
+/// Buffers are told apart by the address of the underlying `gbm_bo`, which libgbm reuses
+/// for the lifetime of the surface, or until it is [recreated](Surface::recreate()).
+#[derive(Debug)]
+pub struct LockedFrontBuffer<T: 'static> {
+    /// The locked buffer, released back to the surface once dropped
+    pub buffer: BufferObject<T>,
+    /// Number of front buffers locked since this buffer was locked the last time,
+    /// including this one
+    ///
+    /// E.g. `1` if the same buffer was locked for the previous frame and `2` if the
+    /// surface alternates between two buffers. Like the buffer age of
+    /// `EGL_EXT_buffer_age`, the content of the buffer before rendering this frame
+    /// was the one of the frame `age` frames ago. `0` if [`Self::is_new`] is set.
+    pub age: u32,
+    /// Whether this is the first time the buffer was locked from the surface
+    ///
+    /// The buffer was newly allocated by the surface and has no userdata yet, e.g. no
+    /// framebuffer created for it, and its previous content is undefined.
+    pub is_new: bool,
+}
+
 /// Errors that may happen when locking the front buffer
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct FrontBufferError;
+#[non_exhaustive]
+pub enum FrontBufferError {
+    /// libgbm failed to lock the front buffer
+    LockFailed {
+        /// The `errno` set by libgbm, if any
+        errno: Option<i32>,
+    },
+    /// The locked front buffer doesn't match the dimensions or format of the surface
+    ///
+    /// Only returned if enabled by [`Surface::with_front_buffer_verification()`].
+    SurfaceMismatch {
+        /// Width of the locked buffer
+        width: u32,
+        /// Height of the locked buffer
+        height: u32,
+        /// Format of the locked buffer
+        format: Format,
+    },
+}
 
 impl fmt::Display for FrontBufferError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Unknown error")
+        match self {
+            FrontBufferError::LockFailed { errno: Some(errno) } => write!(
+                f,
+                "Failed to lock front buffer: {}",
+                io::Error::from_raw_os_error(*errno)
+            ),
+            FrontBufferError::LockFailed { errno: None } => write!(f, "Unknown error"),
+            FrontBufferError::SurfaceMismatch {
+                width,
+                height,
+                format,
+            } => write!(
+                f,
+                "Front buffer ({}x{}, {:?}) doesn't match the surface",
+                width, height, format
+            ),
+        }
     }
 }
 
 impl error::Error for FrontBufferError {}
 
+impl From<FrontBufferError> for io::Error {
+    fn from(err: FrontBufferError) -> Self {
+        let kind = match err {
+            FrontBufferError::LockFailed { errno: Some(errno) } => {
+                io::Error::from_raw_os_error(errno).kind()
+            }
+            _ => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+// Reset `errno`, so an error code can be attributed to the following call
+pub(crate) fn clear_errno() {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    unsafe {
+        *libc::__errno_location() = 0
+    };
+    #[cfg(any(target_os = "freebsd", target_os = "dragonfly", target_os = "macos"))]
+    unsafe {
+        *libc::__error() = 0
+    };
+    #[cfg(any(target_os = "openbsd", target_os = "netbsd"))]
+    unsafe {
+        *libc::__errno() = 0
+    };
+}
+
 impl<T: 'static> Surface<T> {
     ///  Return whether or not a surface has free (non-locked) buffers
     ///
@@ -40,6 +165,10 @@ impl<T: 'static> Surface<T> {
     /// buffer, but after one or more buffers
     /// [have been locked](Self::lock_front_buffer()),
     /// the application must check for a free buffer before rendering.
+    ///
+    /// This is unreliable on some drivers, e.g. the Vivante driver reports free buffers
+    /// while locking the front buffer fails. Use [`Self::try_acquire()`] to attempt
+    /// locking without relying on this.
     pub fn has_free_buffers(&self) -> bool {
         unsafe { ffi::gbm_surface_has_free_buffers(*self.ffi) != 0 }
     }
@@ -49,7 +178,15 @@ impl<T: 'static> Surface<T> {
     /// Locks rendering to the surface's current front buffer and returns
     /// a handle to the underlying [`BufferObject`].
     ///
-    /// If an error occurs a [`FrontBufferError`] is returned.
+    /// If an error occurs a [`FrontBufferError`] is returned, including the `errno` set by
+    /// libgbm, if any. If front buffer verification
+    /// is [enabled](Self::with_front_buffer_verification()), a buffer not matching the
+    /// dimensions and format of the surface is released again and
+    /// [`FrontBufferError::SurfaceMismatch`] is returned.
+    ///
+    /// Dropping the returned buffer object releases it back to the surface. Any userdata
+    /// attached to it is kept and available again, once the surface hands out the same
+    /// buffer in a later call.
     ///
     /// # Safety
     /// This function must be called exactly once after calling
@@ -57,32 +194,445 @@ impl<T: 'static> Surface<T> {
     /// on the surface or two or more times after `eglSwapBuffers` is an
     /// error and may cause undefined behavior.
     pub unsafe fn lock_front_buffer(&self) -> Result<BufferObject<T>, FrontBufferError> {
+        self.lock_front_buffer_with_age()
+            .map(|locked| locked.buffer)
+    }
+
+    /// Lock the surface's current front buffer, returning its age
+    ///
+    /// Same as [`Self::lock_front_buffer()`], but additionally reports whether the
+    /// underlying buffer was locked before and how many frames ago, e.g. to reuse the
+    /// framebuffer created for it and to only redraw the damage of the frames since.
+    /// See [`LockedFrontBuffer`] for the exact semantics.
+    ///
+    /// # Safety
+    /// Same as for [`Self::lock_front_buffer()`].
+    pub unsafe fn lock_front_buffer_with_age(
+        &self,
+    ) -> Result<LockedFrontBuffer<T>, FrontBufferError> {
+        clear_errno();
         let buffer_ptr = ffi::gbm_surface_lock_front_buffer(*self.ffi);
         if !buffer_ptr.is_null() {
+            let age = {
+                let mut locks = self.locks.lock().unwrap();
+                locks.frames += 1;
+                let frame = locks.frames;
+                locks.locked += 1;
+                match locks
+                    .seen
+                    .iter_mut()
+                    .find(|(ptr, _)| *ptr == buffer_ptr as usize)
+                {
+                    Some((_, last)) => {
+                        let age = frame - std::mem::replace(last, frame);
+                        Some(u32::try_from(age).unwrap_or(u32::MAX))
+                    }
+                    None => {
+                        locks.seen.push((buffer_ptr as usize, frame));
+                        None
+                    }
+                }
+            };
             let surface_ptr = self.ffi.clone();
-            let buffer = BufferObject {
-                ffi: Ptr::new(buffer_ptr, move |ptr| {
+            let locks = self.locks.clone();
+            let mut buffer = BufferObject::from_ptr(
+                Ptr::new(buffer_ptr, move |ptr| {
                     ffi::gbm_surface_release_buffer(*surface_ptr, ptr);
+                    locks.lock().unwrap().locked -= 1;
                 }),
-                _device: self._device.clone(),
-                _userdata: std::marker::PhantomData,
-            };
-            Ok(buffer)
+                self._device.clone(),
+                self.device_fd,
+                None,
+            );
+            buffer.surface_owned = true;
+            if self.verify_front_buffer
+                && (buffer.width() != self.width
+                    || buffer.height() != self.height
+                    || buffer.format() != self.format)
+            {
+                return Err(FrontBufferError::SurfaceMismatch {
+                    width: buffer.width(),
+                    height: buffer.height(),
+                    format: buffer.format(),
+                });
+            }
+            Ok(LockedFrontBuffer {
+                buffer,
+                age: age.unwrap_or(0),
+                is_new: age.is_none(),
+            })
         } else {
-            Err(FrontBufferError)
+            let errno = io::Error::last_os_error()
+                .raw_os_error()
+                .filter(|errno| *errno != 0);
+            let locks = self.locks.lock().unwrap();
+            if locks.locked > 0 && locks.locked >= locks.seen.len() {
+                log::warn!(
+                    "Failed to lock front buffer while all {} known buffers of the surface are locked, \
+                     release buffers before locking new ones",
+                    locks.locked
+                );
+            }
+            Err(FrontBufferError::LockFailed { errno })
+        }
+    }
+
+    /// Attempt to lock the surface's current front buffer
+    ///
+    /// Unlike checking [`Self::has_free_buffers()`] before locking, which is unreliable on
+    /// some drivers, this just attempts to lock the front buffer and returns `None` if
+    /// that fails. See [`Self::lock_front_buffer()`] for the error.
+    ///
+    /// # Safety
+    /// Same as for [`Self::lock_front_buffer()`].
+    pub unsafe fn try_acquire(&self) -> Option<BufferObject<T>> {
+        self.lock_front_buffer().ok()
+    }
+
+    /// Number of buffers that can be locked at the same time
+    ///
+    /// libgbm doesn't expose the size of the buffer pool of a surface, so this is learned
+    /// at runtime from the distinct buffers returned by [`Self::lock_front_buffer()`].
+    /// The value is thus a lower bound, that grows until the whole pool has been seen,
+    /// and `None` until the first buffer has been locked.
+    ///
+    /// Holding this many buffers leaves no buffer to render the next frame into.
+    /// Failing to lock a front buffer while all known buffers are held is logged as a warning.
+    pub fn max_concurrent_locks(&self) -> Option<usize> {
+        let locks = self.locks.lock().unwrap();
+        if locks.seen.is_empty() {
+            None
+        } else {
+            Some(locks.seen.len())
+        }
+    }
+
+    /// Cycle through the buffer pool of the surface to discover its buffers
+    ///
+    /// libgbm doesn't expose the buffers of a surface before they are locked, so this
+    /// renders up to `count` frames by calling `swap_buffers`, which must call
+    /// `eglSwapBuffers` on the EGL surface of this surface, and locks the front buffer
+    /// after each. All locked buffers are held until the end, forcing the driver to hand
+    /// out distinct ones, so this stops early once locking the front buffer fails, which
+    /// is how an exhausted pool shows. [`Self::has_free_buffers()`] isn't consulted, as it
+    /// is unreliable on some drivers. Since drivers might already fail to swap without a
+    /// free buffer, an error of `swap_buffers` only stops the loop as well, once at least
+    /// one buffer was discovered. Returns the handles of the discovered buffers, e.g. to
+    /// create KMS framebuffers for every buffer of the pool before the first page flip.
+    ///
+    /// This has side effects: the content of the frames rendered by `swap_buffers` is
+    /// presented to nobody and all buffers are released again once done, so the next
+    /// frame has to be rendered from scratch. The discovered buffers also count towards
+    /// [`Self::max_concurrent_locks()`]. Userdata attached to the locked buffers, if any,
+    /// is kept.
+    ///
+    /// # Safety
+    /// `swap_buffers` must call `eglSwapBuffers` exactly once when returning successfully,
+    /// so locking the front buffer afterwards follows the rules of
+    /// [`Self::lock_front_buffer()`].
+    pub unsafe fn warm_up(
+        &mut self,
+        count: usize,
+        mut swap_buffers: impl FnMut() -> io::Result<()>,
+    ) -> io::Result<Vec<BufferObjectHandle>> {
+        let mut locked = Vec::with_capacity(count);
+        while locked.len() < count {
+            if let Err(err) = swap_buffers() {
+                if locked.is_empty() {
+                    return Err(err);
+                }
+                log::debug!("Stopping warm up after failing to swap buffers: {}", err);
+                break;
+            }
+            match self.try_acquire() {
+                Some(bo) => locked.push(bo),
+                None => break,
+            }
+        }
+        Ok(locked.iter().map(BufferObject::handle).collect())
+    }
+
+    /// Allocate the first buffer of the surface ahead of the first frame
+    ///
+    /// libgbm allocates the buffers of a surface lazily, when the driver first renders
+    /// into it, so there is no way to trigger the allocation at creation time. This
+    /// renders a single frame through [`Self::warm_up()`] instead, so the allocation
+    /// cost isn't paid by the first [`Self::lock_front_buffer()`] of the actual first
+    /// frame. This is best-effort: drivers are free to reallocate buffers later on.
+    /// Returns the handle of the allocated buffer, or `None` if no buffer could be locked.
+    ///
+    /// The side effects of [`Self::warm_up()`] apply.
+    ///
+    /// # Safety
+    /// Same as [`Self::warm_up()`].
+    pub unsafe fn prewarm(
+        &mut self,
+        swap_buffers: impl FnMut() -> io::Result<()>,
+    ) -> io::Result<Option<BufferObjectHandle>> {
+        Ok(self.warm_up(1, swap_buffers)?.into_iter().next())
+    }
+
+    /// Width of the surface as requested on creation
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the surface as requested on creation
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Format of the surface as requested on creation
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Check whether buffers of the surface can be scanned out on a DRM plane
+    ///
+    /// `plane_formats` are the format and modifier pairs supported by the plane, e.g. as
+    /// parsed from its `IN_FORMATS` property. As the driver picks one of the modifiers the
+    /// surface was created with, all of them have to be supported by the plane. Surfaces
+    /// created without explicit modifiers use an implicit layout, which can't be checked,
+    /// so for them only the format has to be supported.
+    ///
+    /// Passing this check doesn't guarantee that an atomic commit succeeds, e.g. because
+    /// of bandwidth or scaling limitations of the plane.
+    #[cfg(feature = "drm-support")]
+    pub fn is_compatible_with_plane(&self, plane_formats: &[(Format, Modifier)]) -> bool {
+        let supported = plane_formats
+            .iter()
+            .filter(|(format, _)| *format == self.format)
+            .map(|(_, modifier)| *modifier)
+            .collect::<Vec<_>>();
+        match &self.modifiers {
+            Some(modifiers) => {
+                !modifiers.is_empty()
+                    && modifiers
+                        .iter()
+                        .all(|modifier| supported.contains(modifier))
+            }
+            None => !supported.is_empty(),
+        }
+    }
+
+    /// Enable or disable verification of locked front buffers
+    ///
+    /// When enabled, [`Self::lock_front_buffer()`] checks that the dimensions and format
+    /// of every locked buffer match the ones the surface was created with, catching
+    /// drivers returning diverging buffers. Disabled by default to avoid the per-frame
+    /// overhead.
+    pub fn with_front_buffer_verification(mut self, verify: bool) -> Self {
+        self.verify_front_buffer = verify;
+        self
+    }
+
+    /// Reset the bookkeeping of the buffers handed out by the surface
+    ///
+    /// Forgets the buffers learned by [`Self::max_concurrent_locks()`] and the history used
+    /// for [`Self::lock_front_buffer_with_age()`], so every buffer is reported as new
+    /// again, e.g. after recovering from a GPU reset or a failed page flip, when the
+    /// content of all buffers has to be considered lost.
+    ///
+    /// Locked buffers are owned by their [`BufferObject`] handles rather than borrowed
+    /// from the surface, so they can't be released forcibly without invalidating those
+    /// handles. They have to be dropped before, e.g. with [`Swapchain::release()`], as
+    /// taking `&mut self` doesn't guarantee this. Otherwise an error of kind
+    /// [`io::ErrorKind::Other`] is returned and the surface is left unchanged.
+    ///
+    /// [`Swapchain::release()`]: crate::Swapchain::release()
+    pub fn reset_buffer_tracking(&mut self) -> io::Result<()> {
+        let mut locks = self.locks.lock().unwrap();
+        if locks.locked > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Cannot reset surface while {} front buffers are locked",
+                    locks.locked
+                ),
+            ));
+        }
+        *locks = LockState::default();
+        Ok(())
+    }
+
+    /// Recreate the surface with new dimensions
+    ///
+    /// libgbm doesn't support resizing surfaces, so this creates a new underlying
+    /// `gbm_surface` with the same format, usage flags and modifiers the surface was
+    /// created with and replaces the old one, e.g. when the window is resized.
+    ///
+    /// All buffers locked from the surface must be released before, otherwise an error
+    /// of kind [`io::ErrorKind::Other`] is returned and the surface is left unchanged.
+    /// Any userdata attached to buffers of the old surface is dropped. EGL surfaces
+    /// created for the old surface must be destroyed and created again.
+    ///
+    /// Surfaces wrapped with [`Self::from_raw()`] can't be recreated, as the usage flags
+    /// and modifiers they were created with are unknown, and fail with an error of kind
+    /// [`io::ErrorKind::Unsupported`].
+    pub fn recreate(&mut self, width: u32, height: u32) -> io::Result<()> {
+        if !self.creation_params_known {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Cannot recreate a surface with unknown usage flags and modifiers",
+            ));
+        }
+        let locked = self.locks.lock().unwrap().locked;
+        if locked > 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Cannot recreate surface while {} front buffers are locked",
+                    locked
+                ),
+            ));
+        }
+
+        let format = self.format as u32;
+        let ptr = match (&self.modifiers, self.usage) {
+            (None, usage) => unsafe {
+                ffi::gbm_surface_create(
+                    *self._device,
+                    width,
+                    height,
+                    format,
+                    usage.unwrap_or(BufferObjectFlags::empty()).bits(),
+                )
+            },
+            (Some(modifiers), None) => {
+                crate::symbols::SURFACE_CREATE_WITH_MODIFIERS.require()?;
+                let mods = modifiers.iter().map(|m| (*m).into()).collect::<Vec<u64>>();
+                unsafe {
+                    ffi::gbm_surface_create_with_modifiers(
+                        *self._device,
+                        width,
+                        height,
+                        format,
+                        mods.as_ptr(),
+                        mods.len() as u32,
+                    )
+                }
+            }
+            (Some(modifiers), Some(usage)) => {
+                crate::symbols::SURFACE_CREATE_WITH_MODIFIERS2.require()?;
+                let mods = modifiers.iter().map(|m| (*m).into()).collect::<Vec<u64>>();
+                unsafe {
+                    ffi::gbm_surface_create_with_modifiers2(
+                        *self._device,
+                        width,
+                        height,
+                        format,
+                        mods.as_ptr(),
+                        mods.len() as u32,
+                        usage.bits(),
+                    )
+                }
+            }
+        };
+        if ptr.is_null() {
+            return Err(io::Error::last_os_error());
         }
+
+        self.ffi = Ptr::new(ptr, |ptr| unsafe { ffi::gbm_surface_destroy(ptr) });
+        self.locks = Arc::new(Mutex::new(LockState::default()));
+        self.width = width;
+        self.height = height;
+        Ok(())
+    }
+
+    /// Wrap a raw `gbm_surface` created outside of this crate, e.g. through EGL
+    ///
+    /// libgbm provides no way to query the dimensions and format of a surface, so the
+    /// ones it was created with must be given. The usage flags and modifiers are unknown
+    /// as well, so the returned surface can't be [recreated](Self::recreate()).
+    ///
+    /// If `owned` is `true`, the surface is destroyed with `gbm_surface_destroy` once
+    /// the returned `Surface` and all buffers locked from it are dropped. Otherwise the
+    /// caller stays responsible for destroying it after that. Locked buffers are always
+    /// released back to the surface with `gbm_surface_release_buffer` when dropped,
+    /// regardless of ownership.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be a valid `gbm_surface` created on `device`, matching `width`, `height`
+    /// and `format`, and must not be destroyed by anyone else, while the returned `Surface`
+    /// or any buffer locked from it is alive. If `owned` is `true`, ownership of the
+    /// surface is transferred and it must not be destroyed elsewhere at all.
+    pub unsafe fn from_raw<D: AsFd>(
+        ptr: *mut ffi::gbm_surface,
+        device: &Device<D>,
+        width: u32,
+        height: u32,
+        format: Format,
+        owned: bool,
+    ) -> Surface<T> {
+        let ffi = if owned {
+            Ptr::new(ptr, |ptr| ffi::gbm_surface_destroy(ptr))
+        } else {
+            Ptr::new(ptr, |_| {})
+        };
+        Surface::from_ptr(
+            ffi,
+            device.ffi.clone(),
+            device.raw_fd,
+            width,
+            height,
+            format,
+        )
     }
 
     pub(crate) unsafe fn new(
         ffi: *mut ffi::gbm_surface,
         device: Ptr<ffi::gbm_device>,
+        device_fd: RawFd,
+        width: u32,
+        height: u32,
+        format: Format,
+    ) -> Surface<T> {
+        Surface::from_ptr(
+            Ptr::new(ffi, |ptr| ffi::gbm_surface_destroy(ptr)),
+            device,
+            device_fd,
+            width,
+            height,
+            format,
+        )
+    }
+
+    fn from_ptr(
+        ffi: Ptr<ffi::gbm_surface>,
+        device: Ptr<ffi::gbm_device>,
+        device_fd: RawFd,
+        width: u32,
+        height: u32,
+        format: Format,
     ) -> Surface<T> {
         Surface {
-            ffi: Ptr::new(ffi, |ptr| ffi::gbm_surface_destroy(ptr)),
+            ffi,
             _device: device,
+            device_fd,
+            locks: Arc::new(Mutex::new(LockState::default())),
+            width,
+            height,
+            format,
+            creation_params_known: false,
+            usage: None,
+            modifiers: None,
+            verify_front_buffer: false,
             _bo_userdata: PhantomData,
         }
     }
+
+    // Record the usage flags and modifiers passed on creation for `recreate`
+    pub(crate) fn with_creation_params(
+        mut self,
+        usage: Option<BufferObjectFlags>,
+        modifiers: Option<Vec<Modifier>>,
+    ) -> Self {
+        self.creation_params_known = true;
+        self.usage = usage;
+        self.modifiers = modifiers.map(Vec::into_boxed_slice);
+        self
+    }
 }
 
 impl<T: 'static> AsRaw<ffi::gbm_surface> for Surface<T> {
@@ -0,0 +1,98 @@
+use crate::{BufferObject, Surface};
+
+use std::collections::VecDeque;
+use std::io::Result as IoResult;
+
+/// Acquire and release bookkeeping for the front buffers of a [`Surface`]
+///
+/// A swapchain holds on to the buffers locked for the most recent frames and releases
+/// the oldest one back to the surface once the buffer of the next frame is acquired,
+/// so that the surface never runs out of free buffers by forgetting to release one.
+///
+/// ```rust,no_run
+/// # fn render_loop(surface: gbm::Surface<()>) -> std::io::Result<()> {
+/// let mut swapchain = gbm::Swapchain::new(surface);
+/// loop {
+///     // render into the surface and call `eglSwapBuffers`
+///     let bo = unsafe { swapchain.next_frame()? };
+///     // present `bo` and wait for the page flip to complete
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Swapchain<T: 'static> {
+    surface: Surface<T>,
+    // Locked buffers, oldest first
+    locked: VecDeque<BufferObject<T>>,
+    desired_buffers: usize,
+}
+
+impl<T: 'static> Swapchain<T> {
+    /// Create a new double buffered swapchain for the given surface
+    pub fn new(surface: Surface<T>) -> Swapchain<T> {
+        Swapchain::with_desired_buffers(surface, 2)
+    }
+
+    /// Create a new swapchain for the given surface, using `desired_buffers` buffers
+    ///
+    /// The swapchain holds at most `desired_buffers - 1` locked buffers, leaving one
+    /// buffer to render into, e.g. `2` for double and `3` for triple buffering.
+    /// Values smaller than `2` are treated as `2`.
+    ///
+    /// This is only a hint: libgbm doesn't allow controlling the number of buffers
+    /// allocated for a surface, so the driver ultimately decides. The swapchain only
+    /// respects the hint when deciding, when to release buffers.
+    pub fn with_desired_buffers(surface: Surface<T>, desired_buffers: usize) -> Swapchain<T> {
+        let desired_buffers = desired_buffers.max(2);
+        Swapchain {
+            surface,
+            locked: VecDeque::with_capacity(desired_buffers),
+            desired_buffers,
+        }
+    }
+
+    /// The number of buffers the swapchain was created for
+    pub fn desired_buffers(&self) -> usize {
+        self.desired_buffers
+    }
+
+    /// Lock the front buffer of the surface and release the buffer of the oldest frame
+    ///
+    /// The oldest buffer is released after the new one has been locked, if the swapchain
+    /// would hold more than [`desired_buffers - 1`](Self::desired_buffers()) buffers
+    /// otherwise. The released buffer might be reused for rendering the next frame, so
+    /// only start rendering the next frame, once it isn't in use anymore, e.g. after
+    /// the page flip to the returned buffer completed.
+    ///
+    /// # Safety
+    /// Same as for [`Surface::lock_front_buffer()`], this function must be called exactly
+    /// once after calling `eglSwapBuffers`.
+    pub unsafe fn next_frame(&mut self) -> IoResult<&mut BufferObject<T>> {
+        let bo = self.surface.lock_front_buffer()?;
+        self.locked.push_back(bo);
+        while self.locked.len() > self.desired_buffers - 1 {
+            self.locked.pop_front();
+        }
+        Ok(self.locked.back_mut().unwrap())
+    }
+
+    /// The buffer of the current frame, if any
+    pub fn current(&self) -> Option<&BufferObject<T>> {
+        self.locked.back()
+    }
+
+    /// Release all buffers held by the swapchain back to the surface
+    pub fn release(&mut self) {
+        self.locked.clear();
+    }
+
+    /// The underlying surface
+    pub fn surface(&self) -> &Surface<T> {
+        &self.surface
+    }
+
+    /// Release all held buffers and return the underlying surface
+    pub fn into_surface(self) -> Surface<T> {
+        self.surface
+    }
+}
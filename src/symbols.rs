@@ -0,0 +1,118 @@
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::io::{Error as IoError, ErrorKind, Result as IoResult};
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+const UNKNOWN: u8 = 0;
+const PRESENT: u8 = 1;
+const MISSING: u8 = 2;
+
+// A libgbm function, that is not available in all versions of libgbm.
+//
+// Calling a function missing from the loaded libgbm aborts the process, if the
+// executable was linked with lazy binding. Its presence is looked up once via `dlsym`.
+pub(crate) struct OptionalSymbol {
+    // Nul-terminated symbol name
+    name: &'static str,
+    state: AtomicU8,
+}
+
+impl OptionalSymbol {
+    const fn new(name: &'static str) -> OptionalSymbol {
+        OptionalSymbol {
+            name,
+            state: AtomicU8::new(UNKNOWN),
+        }
+    }
+
+    pub(crate) fn is_present(&self) -> bool {
+        match self.state.load(Ordering::Relaxed) {
+            PRESENT => true,
+            MISSING => false,
+            _ => {
+                let present =
+                    ffi::has_symbol(CStr::from_bytes_with_nul(self.name.as_bytes()).unwrap());
+                self.state
+                    .store(if present { PRESENT } else { MISSING }, Ordering::Relaxed);
+                present
+            }
+        }
+    }
+
+    // Fails with `ErrorKind::Unsupported` if the symbol is missing
+    pub(crate) fn require(&self) -> IoResult<()> {
+        if self.is_present() {
+            Ok(())
+        } else {
+            Err(IoError::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "`{}` is not provided by the loaded libgbm",
+                    self.name.trim_end_matches('\0')
+                ),
+            ))
+        }
+    }
+}
+
+pub(crate) static BO_CREATE_WITH_MODIFIERS: OptionalSymbol =
+    OptionalSymbol::new("gbm_bo_create_with_modifiers\0");
+pub(crate) static BO_CREATE_WITH_MODIFIERS2: OptionalSymbol =
+    OptionalSymbol::new("gbm_bo_create_with_modifiers2\0");
+pub(crate) static SURFACE_CREATE_WITH_MODIFIERS: OptionalSymbol =
+    OptionalSymbol::new("gbm_surface_create_with_modifiers\0");
+pub(crate) static SURFACE_CREATE_WITH_MODIFIERS2: OptionalSymbol =
+    OptionalSymbol::new("gbm_surface_create_with_modifiers2\0");
+pub(crate) static BO_GET_FD_FOR_PLANE: OptionalSymbol =
+    OptionalSymbol::new("gbm_bo_get_fd_for_plane\0");
+
+// Check whether the loaded libgbm provides the function `name`
+pub(crate) fn has_symbol(name: &str) -> bool {
+    match CString::new(name) {
+        Ok(name) => ffi::has_symbol(&name),
+        Err(_) => false,
+    }
+}
+
+/// Best-effort description of the version of the loaded libgbm, e.g. for bug reports
+///
+/// libgbm doesn't report its version, so this derives the range of Mesa releases from the
+/// version-gated functions it provides (see [`ffi::runtime_capabilities()`]) and appends
+/// the path of the loaded library, e.g. `"Mesa >= 21.3 (/usr/lib/libgbm.so.1.0.0)"`.
+/// Other implementations of libgbm are reported as if they were Mesa releases
+/// providing the same functions.
+///
+/// Returns `None` if the loaded libgbm can't be located.
+pub fn library_version() -> Option<String> {
+    let mut info = MaybeUninit::<libc::Dl_info>::uninit();
+    let found = unsafe {
+        libc::dladdr(
+            ffi::gbm_create_device as *const libc::c_void,
+            info.as_mut_ptr(),
+        )
+    };
+    if found == 0 {
+        return None;
+    }
+    let info = unsafe { info.assume_init() };
+    if info.dli_fname.is_null() {
+        return None;
+    }
+    let path = unsafe { CStr::from_ptr(info.dli_fname) }.to_string_lossy();
+    let path = fs::canonicalize(&*path)
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| path.into_owned());
+
+    let caps = ffi::runtime_capabilities();
+    let range = if caps.bo_create_with_modifiers2 {
+        ">= 21.3"
+    } else if caps.bo_get_fd_for_plane {
+        "21.1 - 21.2"
+    } else if caps.bo_create_with_modifiers {
+        "17.1 - 21.0"
+    } else {
+        "< 17.1"
+    };
+    Some(format!("Mesa {} ({})", range, path))
+}
@@ -0,0 +1,79 @@
+use crate::{BufferObject, Format, Modifier};
+
+use std::io::{Error as IoError, Result as IoResult};
+use std::os::unix::io::{AsRawFd, OwnedFd};
+
+/// Layout and memory of a [`BufferObject`] for importing it as external texture in `wgpu`
+///
+/// The fields map to the structures wgpu-hal's Vulkan backend expects when creating an
+/// image from a dma buffer via `VK_EXT_image_drm_format_modifier`, before wrapping it
+/// with `create_texture_from_hal`: `VkImageDrmFormatModifierExplicitCreateInfoEXT` takes
+/// the [`modifier`](Self::modifier) and one `VkSubresourceLayout` per
+/// [plane](Self::planes), while the file descriptors are imported as memory using
+/// `VkImportMemoryFdInfoKHR`. Choosing the `wgpu::TextureFormat` matching the
+/// [`format`](Self::format) is left to the caller.
+///
+/// Created by [`BufferObject::wgpu_dma_buf_descriptor()`].
+#[derive(Debug)]
+pub struct WgpuDmaBufDescriptor {
+    /// Width of the buffer
+    pub width: u32,
+    /// Height of the buffer
+    pub height: u32,
+    /// Format of the buffer
+    pub format: Format,
+    /// Modifier of the buffer, i.e. `drmFormatModifier`
+    pub modifier: Modifier,
+    /// Whether the planes are backed by different dma buffers,
+    /// requiring `VK_IMAGE_CREATE_DISJOINT_BIT`
+    pub disjoint: bool,
+    /// Layout and memory of every plane
+    pub planes: Vec<WgpuDmaBufPlane>,
+}
+
+/// Layout and memory of a single plane of a [`WgpuDmaBufDescriptor`]
+#[derive(Debug)]
+pub struct WgpuDmaBufPlane {
+    /// Exported dma buffer of the plane
+    pub fd: OwnedFd,
+    /// Offset of the plane into the dma buffer, i.e. `VkSubresourceLayout::offset`
+    pub offset: u64,
+    /// Stride of the plane, i.e. `VkSubresourceLayout::rowPitch`
+    pub row_pitch: u64,
+}
+
+impl<T: 'static> BufferObject<T> {
+    /// Export the buffer object for importing it as external texture in `wgpu`
+    ///
+    /// Every plane is exported as separate file descriptor, see [`WgpuDmaBufDescriptor`].
+    pub fn wgpu_dma_buf_descriptor(&self) -> IoResult<WgpuDmaBufDescriptor> {
+        let planes = self
+            .planes()
+            .map(|plane| {
+                Ok(WgpuDmaBufPlane {
+                    fd: self.fd_for_plane(plane.index)?,
+                    offset: plane.offset as u64,
+                    row_pitch: plane.stride as u64,
+                })
+            })
+            .collect::<IoResult<Vec<_>>>()?;
+
+        let mut inodes = Vec::with_capacity(planes.len());
+        for plane in &planes {
+            let mut stat = std::mem::MaybeUninit::<libc::stat>::uninit();
+            if unsafe { libc::fstat(plane.fd.as_raw_fd(), stat.as_mut_ptr()) } != 0 {
+                return Err(IoError::last_os_error());
+            }
+            inodes.push(unsafe { stat.assume_init() }.st_ino);
+        }
+
+        Ok(WgpuDmaBufDescriptor {
+            width: self.width(),
+            height: self.height(),
+            format: self.format(),
+            modifier: self.modifier(),
+            disjoint: inodes.windows(2).any(|pair| pair[0] != pair[1]),
+            planes,
+        })
+    }
+}
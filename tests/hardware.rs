@@ -0,0 +1,358 @@
+//! Round-trip tests against a real GBM device
+//!
+//! These tests need a GPU and are only built with the `hardware-tests` feature. The device
+//! node to test is taken from the `GBM_TEST_DEVICE` environment variable, e.g.
+//!
+//! ```sh
+//! GBM_TEST_DEVICE=/dev/dri/renderD128 cargo test --features hardware-tests --test hardware
+//! ```
+//!
+//! Without the variable every test passes without doing anything, so the feature can be
+//! enabled unconditionally on CI runners and the variable set only on the ones with a GPU.
+#![cfg(feature = "hardware-tests")]
+
+use gbm::{AsRaw, BufferObject, BufferObjectFlags, Device, DmaBufFrame, Format, Modifier, Surface};
+use std::ffi::{c_void, CStr};
+use std::fs::{File, OpenOptions};
+use std::os::unix::io::AsFd;
+use std::ptr;
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 32;
+
+fn open_device() -> Option<Device<File>> {
+    let path = match std::env::var_os("GBM_TEST_DEVICE") {
+        Some(path) => path,
+        None => {
+            eprintln!("GBM_TEST_DEVICE is not set, skipping");
+            return None;
+        }
+    };
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap_or_else(|err| panic!("Failed to open {:?}: {}", path, err));
+    Some(Device::new(file).expect("Failed to create GBM device"))
+}
+
+fn create_linear(device: &Device<File>) -> BufferObject<()> {
+    device
+        .create_buffer_object(
+            WIDTH,
+            HEIGHT,
+            Format::Argb8888,
+            BufferObjectFlags::LINEAR | BufferObjectFlags::RENDERING,
+        )
+        .expect("Failed to create linear buffer object")
+}
+
+// Tightly packed pixel data, distinct for every pixel
+fn pattern() -> Vec<u8> {
+    (0..WIDTH * HEIGHT)
+        .flat_map(|pixel| pixel.to_le_bytes())
+        .collect()
+}
+
+fn read_packed(bo: &BufferObject<()>) -> Vec<u8> {
+    bo.map(0, 0, WIDTH, HEIGHT, |mapping| {
+        let stride = mapping.stride() as usize;
+        let row_bytes = WIDTH as usize * 4;
+        mapping
+            .buffer()
+            .chunks(stride)
+            .take(HEIGHT as usize)
+            .flat_map(|row| &row[..row_bytes])
+            .copied()
+            .collect()
+    })
+    .expect("Failed to map buffer object")
+}
+
+#[test]
+fn create_reports_requested_layout() {
+    let Some(device) = open_device() else { return };
+    let bo = create_linear(&device);
+    assert_eq!(bo.width(), WIDTH);
+    assert_eq!(bo.height(), HEIGHT);
+    assert_eq!(bo.format(), Format::Argb8888);
+    assert!(bo.stride() >= WIDTH * 4);
+    assert!(bo.plane_count() >= 1);
+}
+
+#[test]
+fn write_map_round_trip() {
+    let Some(device) = open_device() else { return };
+    let mut bo = create_linear(&device);
+    let data = pattern();
+    bo.write_from(&mut &data[..])
+        .expect("Failed to write buffer object");
+    assert_eq!(read_packed(&bo), data);
+}
+
+#[test]
+fn map_mut_round_trip() {
+    let Some(device) = open_device() else { return };
+    let mut bo = create_linear(&device);
+    bo.map_mut(0, 0, WIDTH, HEIGHT, |mapping| {
+        mapping.buffer_mut().fill(0x5a);
+    })
+    .expect("Failed to map buffer object");
+    assert!(read_packed(&bo).iter().all(|byte| *byte == 0x5a));
+}
+
+#[test]
+fn export_import_round_trip() {
+    let Some(device) = open_device() else { return };
+    let mut bo = create_linear(&device);
+    let data = pattern();
+    bo.write_from(&mut &data[..])
+        .expect("Failed to write buffer object");
+
+    let frame = DmaBufFrame::from_bo(&bo).expect("Failed to export buffer object");
+    let imported: BufferObject<()> = device
+        .import(
+            frame.import_source().expect("Invalid export"),
+            BufferObjectFlags::LINEAR | BufferObjectFlags::RENDERING,
+        )
+        .expect("Failed to import buffer object");
+    assert_eq!(imported.width(), WIDTH);
+    assert_eq!(imported.height(), HEIGHT);
+    assert_eq!(imported.format(), Format::Argb8888);
+    assert_eq!(read_packed(&imported), data);
+}
+
+#[test]
+fn shared_device_allocates_on_other_thread() {
+    let Some(device) = open_device() else { return };
+    let device = device.into_shared();
+    let remote = device.clone();
+    let bo: BufferObject<()> = std::thread::spawn(move || {
+        remote.create_buffer_object(
+            WIDTH,
+            HEIGHT,
+            Format::Argb8888,
+            BufferObjectFlags::RENDERING,
+        )
+    })
+    .join()
+    .unwrap()
+    .expect("Failed to create buffer object on another thread");
+    assert_eq!((bo.width(), bo.height()), (WIDTH, HEIGHT));
+}
+
+#[test]
+fn planar_handles() {
+    let Some(device) = open_device() else { return };
+    if !device.is_format_supported(Format::Nv12, BufferObjectFlags::RENDERING) {
+        eprintln!("NV12 is not supported, skipping");
+        return;
+    }
+    let bo: BufferObject<()> = device
+        .create_buffer_object(WIDTH, HEIGHT, Format::Nv12, BufferObjectFlags::RENDERING)
+        .expect("Failed to create NV12 buffer object");
+    let planes = bo.planes().collect::<Vec<_>>();
+    assert_eq!(planes.len(), bo.plane_count() as usize);
+    for plane in planes {
+        assert!(plane.stride > 0);
+        let _ = bo.handle_for_plane(plane.index);
+    }
+}
+
+#[test]
+fn import_rejects_plane_count_mismatch() {
+    let Some(device) = open_device() else { return };
+    let bo = create_linear(&device);
+    let fd = bo.fd().expect("Failed to export buffer object");
+    let stride = bo.stride() as i32;
+    let result = device.import_buffer_object_from_dma_buf_with_modifiers::<()>(
+        2,
+        [Some(fd.as_fd()), Some(fd.as_fd()), None, None],
+        WIDTH,
+        HEIGHT,
+        Format::Argb8888,
+        BufferObjectFlags::LINEAR,
+        [stride, stride, 0, 0],
+        [0, 0, 0, 0],
+        Modifier::Linear,
+    );
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+}
+
+// Minimal EGL setup rendering to a `Surface`, loaded at runtime to avoid a build dependency
+// on libEGL. `eglSwapBuffers` has to be called before every lock of the front buffer.
+struct Egl {
+    display: *mut c_void,
+    surface: *mut c_void,
+    swap_buffers: SwapBuffers,
+}
+
+type SwapBuffers = unsafe extern "C" fn(*mut c_void, *mut c_void) -> u32;
+
+const EGL_NONE: i32 = 0x3038;
+const EGL_PLATFORM_GBM_KHR: u32 = 0x31D7;
+const EGL_SURFACE_TYPE: i32 = 0x3033;
+const EGL_WINDOW_BIT: i32 = 0x0004;
+const EGL_RENDERABLE_TYPE: i32 = 0x3040;
+const EGL_OPENGL_ES2_BIT: i32 = 0x0004;
+const EGL_NATIVE_VISUAL_ID: i32 = 0x302E;
+const EGL_OPENGL_ES_API: u32 = 0x30A0;
+const EGL_CONTEXT_CLIENT_VERSION: i32 = 0x3098;
+
+impl Egl {
+    unsafe fn new<T: 'static>(device: &Device<File>, surface: &Surface<T>) -> Option<Egl> {
+        let lib = libc::dlopen(b"libEGL.so.1\0".as_ptr() as *const _, libc::RTLD_NOW);
+        if lib.is_null() {
+            eprintln!("libEGL.so.1 is not available, skipping");
+            return None;
+        }
+        let sym = |name: &[u8]| {
+            let sym = libc::dlsym(lib, name.as_ptr() as *const _);
+            assert!(
+                !sym.is_null(),
+                "libEGL is missing {:?}",
+                CStr::from_bytes_with_nul(name)
+            );
+            sym
+        };
+        let get_platform_display: unsafe extern "C" fn(
+            u32,
+            *mut c_void,
+            *const isize,
+        ) -> *mut c_void = std::mem::transmute(sym(b"eglGetPlatformDisplay\0"));
+        let initialize: unsafe extern "C" fn(*mut c_void, *mut i32, *mut i32) -> u32 =
+            std::mem::transmute(sym(b"eglInitialize\0"));
+        let bind_api: unsafe extern "C" fn(u32) -> u32 = std::mem::transmute(sym(b"eglBindAPI\0"));
+        let choose_config: unsafe extern "C" fn(
+            *mut c_void,
+            *const i32,
+            *mut *mut c_void,
+            i32,
+            *mut i32,
+        ) -> u32 = std::mem::transmute(sym(b"eglChooseConfig\0"));
+        let get_config_attrib: unsafe extern "C" fn(
+            *mut c_void,
+            *mut c_void,
+            i32,
+            *mut i32,
+        ) -> u32 = std::mem::transmute(sym(b"eglGetConfigAttrib\0"));
+        let create_context: unsafe extern "C" fn(
+            *mut c_void,
+            *mut c_void,
+            *mut c_void,
+            *const i32,
+        ) -> *mut c_void = std::mem::transmute(sym(b"eglCreateContext\0"));
+        let create_window_surface: unsafe extern "C" fn(
+            *mut c_void,
+            *mut c_void,
+            *mut c_void,
+            *const isize,
+        ) -> *mut c_void = std::mem::transmute(sym(b"eglCreatePlatformWindowSurface\0"));
+        let make_current: unsafe extern "C" fn(
+            *mut c_void,
+            *mut c_void,
+            *mut c_void,
+            *mut c_void,
+        ) -> u32 = std::mem::transmute(sym(b"eglMakeCurrent\0"));
+
+        let display = get_platform_display(
+            EGL_PLATFORM_GBM_KHR,
+            device.as_raw() as *mut c_void,
+            ptr::null(),
+        );
+        assert!(!display.is_null(), "eglGetPlatformDisplay failed");
+        assert_eq!(initialize(display, ptr::null_mut(), ptr::null_mut()), 1);
+        assert_eq!(bind_api(EGL_OPENGL_ES_API), 1);
+
+        let attribs = [
+            EGL_SURFACE_TYPE,
+            EGL_WINDOW_BIT,
+            EGL_RENDERABLE_TYPE,
+            EGL_OPENGL_ES2_BIT,
+            EGL_NONE,
+        ];
+        let mut configs = [ptr::null_mut(); 64];
+        let mut count = 0;
+        assert_eq!(
+            choose_config(
+                display,
+                attribs.as_ptr(),
+                configs.as_mut_ptr(),
+                64,
+                &mut count
+            ),
+            1
+        );
+        let config = *configs[..count as usize]
+            .iter()
+            .find(|config| {
+                let mut visual = 0;
+                get_config_attrib(display, **config, EGL_NATIVE_VISUAL_ID, &mut visual) == 1
+                    && visual as u32 == surface.format() as u32
+            })
+            .expect("No EGL config matches the surface format");
+
+        let context_attribs = [EGL_CONTEXT_CLIENT_VERSION, 2, EGL_NONE];
+        let context = create_context(display, config, ptr::null_mut(), context_attribs.as_ptr());
+        assert!(!context.is_null(), "eglCreateContext failed");
+        let egl_surface = create_window_surface(
+            display,
+            config,
+            surface.as_raw() as *mut c_void,
+            ptr::null(),
+        );
+        assert!(
+            !egl_surface.is_null(),
+            "eglCreatePlatformWindowSurface failed"
+        );
+        assert_eq!(make_current(display, egl_surface, egl_surface, context), 1);
+
+        Some(Egl {
+            display,
+            surface: egl_surface,
+            swap_buffers: std::mem::transmute::<*mut c_void, SwapBuffers>(sym(b"eglSwapBuffers\0")),
+        })
+    }
+
+    fn swap_buffers(&self) {
+        assert_eq!(
+            unsafe { (self.swap_buffers)(self.display, self.surface) },
+            1
+        );
+    }
+}
+
+#[test]
+fn surface_userdata_survives_lock_release() {
+    let Some(device) = open_device() else { return };
+    let surface: Surface<u32> = device
+        .create_surface(
+            WIDTH,
+            HEIGHT,
+            Format::Xrgb8888,
+            BufferObjectFlags::RENDERING,
+        )
+        .expect("Failed to create surface");
+    let Some(egl) = (unsafe { Egl::new(&device, &surface) }) else {
+        return;
+    };
+
+    egl.swap_buffers();
+    let mut bo = unsafe { surface.lock_front_buffer() }.expect("Failed to lock front buffer");
+    assert_eq!(bo.userdata(), None);
+    bo.set_userdata(42);
+    let first = bo.as_raw();
+    drop(bo);
+
+    // The surface cycles through a small pool of buffers, so the first one comes back
+    // after a few frames
+    for _ in 0..8 {
+        egl.swap_buffers();
+        let bo = unsafe { surface.lock_front_buffer() }.expect("Failed to lock front buffer");
+        if bo.as_raw() == first {
+            assert_eq!(bo.userdata(), Some(&42));
+            return;
+        }
+    }
+    panic!("The surface never returned the first buffer again");
+}